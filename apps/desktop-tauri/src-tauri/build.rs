@@ -1,3 +1,78 @@
+use std::path::{Path, PathBuf};
+
 fn main() {
+    build_whisper();
     tauri_build::build()
 }
+
+/// Compile the whisper.cpp copy vendored in `packages/whisper-wrapper` as a
+/// CPU-only static library, together with the small C shim in `native/`.
+fn build_whisper() {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let whisper_dir = manifest_dir
+        .join("../../../packages/whisper-wrapper/whisper.cpp")
+        .canonicalize()
+        .expect("vendored whisper.cpp not found in packages/whisper-wrapper");
+    let shim_dir = manifest_dir.join("native");
+
+    println!("cargo:rerun-if-changed={}", shim_dir.display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        whisper_dir.join("include/whisper.h").display()
+    );
+    println!("cargo:rerun-if-changed={}", whisper_dir.join("src").display());
+
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+
+    // The shim must precede the whisper archives on the link line.
+    cc::Build::new()
+        .cpp(true)
+        .std("c++17")
+        .file(shim_dir.join("kotoba_whisper.cpp"))
+        .include(&shim_dir)
+        .include(whisper_dir.join("include"))
+        .include(whisper_dir.join("ggml/include"))
+        .compile("kotoba_whisper");
+
+    let dst = cmake::Config::new(&whisper_dir)
+        .define("BUILD_SHARED_LIBS", "OFF")
+        .define("WHISPER_BUILD_TESTS", "OFF")
+        .define("WHISPER_BUILD_EXAMPLES", "OFF")
+        .define("WHISPER_BUILD_SERVER", "OFF")
+        .define("WHISPER_CURL", "OFF")
+        .define("WHISPER_SDL2", "OFF")
+        .define("WHISPER_FFMPEG", "OFF")
+        .define("GGML_STATIC", "ON")
+        .define("GGML_NATIVE", "OFF")
+        .define("GGML_OPENMP", "OFF")
+        .define("GGML_METAL", "OFF")
+        .define("GGML_CUDA", "OFF")
+        .define("GGML_VULKAN", "OFF")
+        .define("GGML_BLAS", "OFF")
+        .profile("Release")
+        .build_target("whisper")
+        .build();
+
+    let build_dir = dst.join("build");
+    for dir in [build_dir.join("src"), build_dir.join("ggml/src")] {
+        add_search_path(&dir);
+    }
+
+    println!("cargo:rustc-link-lib=static=whisper");
+    println!("cargo:rustc-link-lib=static=ggml");
+    println!("cargo:rustc-link-lib=static=ggml-cpu");
+    println!("cargo:rustc-link-lib=static=ggml-base");
+
+    if target_os == "macos" {
+        println!("cargo:rustc-link-lib=framework=Accelerate");
+    }
+}
+
+/// Multi-config generators (MSVC) put archives in a per-profile subdirectory.
+fn add_search_path(dir: &Path) {
+    println!("cargo:rustc-link-search=native={}", dir.display());
+    println!(
+        "cargo:rustc-link-search=native={}",
+        dir.join("Release").display()
+    );
+}
@@ -0,0 +1,69 @@
+#include "kotoba_whisper.h"
+
+#include "whisper.h"
+
+#include <cstring>
+
+extern "C" struct whisper_context * kotoba_whisper_init(const char * model_path) {
+    whisper_context_params cparams = whisper_context_default_params();
+    cparams.use_gpu = false;
+    cparams.flash_attn = false;
+    return whisper_init_from_file_with_params(model_path, cparams);
+}
+
+static void kotoba_progress(
+    struct whisper_context * /*ctx*/,
+    struct whisper_state * /*state*/,
+    int progress,
+    void * user_data) {
+    const auto * options = static_cast<const kotoba_full_options *>(user_data);
+    options->progress(progress, options->user_data);
+}
+
+static bool kotoba_abort(void * user_data) {
+    const auto * options = static_cast<const kotoba_full_options *>(user_data);
+    return options->abort(options->abort_data) != 0;
+}
+
+extern "C" int32_t kotoba_whisper_full(
+    struct whisper_context * ctx,
+    const kotoba_full_options * options,
+    const float * samples,
+    int32_t n_samples) {
+    whisper_full_params params = whisper_full_default_params(WHISPER_SAMPLING_GREEDY);
+    params.print_progress = false;
+    params.print_realtime = false;
+    params.print_special = false;
+    params.print_timestamps = false;
+    params.suppress_blank = true;
+    params.suppress_nst = true;
+    params.no_timestamps = false;
+
+    if (options->n_threads > 0) {
+        params.n_threads = options->n_threads;
+    }
+
+    const char * language = options->language;
+    if (language == nullptr || language[0] == '\0' || std::strcmp(language, "auto") == 0) {
+        params.language = "auto";
+        params.detect_language = false;
+    } else {
+        params.language = language;
+    }
+
+    if (options->initial_prompt != nullptr && options->initial_prompt[0] != '\0') {
+        params.initial_prompt = options->initial_prompt;
+    }
+
+    if (options->progress != nullptr) {
+        params.progress_callback = kotoba_progress;
+        params.progress_callback_user_data = const_cast<kotoba_full_options *>(options);
+    }
+
+    if (options->abort != nullptr) {
+        params.abort_callback = kotoba_abort;
+        params.abort_callback_user_data = const_cast<kotoba_full_options *>(options);
+    }
+
+    return whisper_full(ctx, params, samples, n_samples);
+}
@@ -0,0 +1,46 @@
+// Narrow C interface over whisper.cpp for the Rust backend.
+//
+// whisper_full_params and whisper_context_params are large structs passed by
+// value, which makes them awkward to mirror from Rust. The shim builds them on
+// the C++ side from the small option struct below; everything else is called
+// directly through whisper.h.
+
+#pragma once
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+struct whisper_context;
+
+typedef struct kotoba_full_options {
+    int32_t n_threads;
+    // NULL or "auto" to let whisper detect the language.
+    const char * language;
+    // Optional decoder prompt; NULL when unused.
+    const char * initial_prompt;
+    // Called with 0-100 as decoding advances; NULL when unused.
+    void (*progress)(int32_t percent, void * user_data);
+    void * user_data;
+    // Polled during inference, possibly from several threads; a non-zero
+    // return stops it. NULL when unused.
+    int32_t (*abort)(void * abort_data);
+    void * abort_data;
+} kotoba_full_options;
+
+// Load a ggml model from disk for CPU inference. Returns NULL on failure.
+struct whisper_context * kotoba_whisper_init(const char * model_path);
+
+// Run the full encoder/decoder pipeline over 16 kHz mono f32 samples.
+// Returns 0 on success.
+int32_t kotoba_whisper_full(
+    struct whisper_context * ctx,
+    const kotoba_full_options * options,
+    const float * samples,
+    int32_t n_samples);
+
+#ifdef __cplusplus
+}
+#endif
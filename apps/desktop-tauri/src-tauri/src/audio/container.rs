@@ -0,0 +1,245 @@
+//! Compressed container decoding (FLAC, Ogg, MP3, M4A/AAC, WebM) via symphonia.
+//!
+//! Symphonia demuxes Opus but has no Opus decoder, so Opus packets — the
+//! default output of the renderer's `MediaRecorder` — go through the pure-Rust
+//! `opus-decoder`.
+
+use super::DecodedAudio;
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Opus always decodes at 48 kHz internally.
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// Largest Opus frame: 120 ms at 48 kHz, per channel.
+const OPUS_MAX_FRAME: usize = 5_760;
+
+/// Decode the first audio track of any container symphonia can probe.
+pub fn decode(path: &Path) -> Result<DecodedAudio, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("Unsupported audio file {}: {e}", path.display()))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| format!("No audio track in {}", path.display()))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    if params.codec == CODEC_TYPE_OPUS {
+        decode_opus(format.as_mut(), track_id, &params)
+    } else {
+        decode_generic(format.as_mut(), track_id, &params)
+    }
+}
+
+fn decode_generic(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+) -> Result<DecodedAudio, String> {
+    let mut decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .map_err(|e| e.to_string())?;
+
+    let mut samples = Vec::new();
+    let mut sample_rate = params.sample_rate;
+    let mut channels = params.channels.map(|c| c.count() as u16);
+
+    while let Some(packet) = next_packet(format)? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(buffer) => {
+                let spec = *buffer.spec();
+                sample_rate.get_or_insert(spec.rate);
+                channels.get_or_insert(spec.channels.count() as u16);
+                let mut interleaved = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+                interleaved.copy_interleaved_ref(buffer);
+                samples.extend_from_slice(interleaved.samples());
+            }
+            // Corrupt packets are skipped rather than failing the whole file.
+            Err(SymphoniaError::DecodeError(e)) => log::warn!("Skipping undecodable packet: {e}"),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: sample_rate.ok_or("Unknown sample rate")?,
+        channels: channels.ok_or("Unknown channel layout")?,
+    })
+}
+
+fn decode_opus(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+) -> Result<DecodedAudio, String> {
+    // OpusHead: magic(8) version(1) channels(1) pre_skip(2, LE) ...
+    let head = params.extra_data.as_deref().unwrap_or_default();
+    let head_channels = head.get(9).copied().map(u16::from);
+    let pre_skip = head
+        .get(10..12)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .unwrap_or(0);
+
+    let channel_count = params
+        .channels
+        .map(|c| c.count() as u16)
+        .or(head_channels)
+        .unwrap_or(1);
+    if !(1..=2).contains(&channel_count) {
+        return Err(format!("Unsupported Opus channel count: {channel_count}"));
+    }
+
+    let channels = channel_count as usize;
+    let mut decoder =
+        opus_decoder::OpusDecoder::new(OPUS_SAMPLE_RATE, channels).map_err(|e| e.to_string())?;
+    let mut frame = vec![0.0f32; OPUS_MAX_FRAME * channels];
+    let mut samples = Vec::new();
+
+    while let Some(packet) = next_packet(format)? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode_float(&packet.data, &mut frame, false) {
+            Ok(per_channel) => samples.extend_from_slice(&frame[..per_channel * channels]),
+            Err(e) => log::warn!("Skipping undecodable Opus packet: {e}"),
+        }
+    }
+
+    // The encoder's lookahead is emitted as leading samples to be discarded.
+    let skip = (pre_skip * channels).min(samples.len());
+    samples.drain(..skip);
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: OPUS_SAMPLE_RATE,
+        channels: channel_count,
+    })
+}
+
+/// Next packet, or `None` at end of stream.
+fn next_packet(
+    format: &mut dyn FormatReader,
+) -> Result<Option<symphonia::core::formats::Packet>, String> {
+    match format.next_packet() {
+        Ok(packet) => Ok(Some(packet)),
+        Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Ok(None)
+        }
+        // Chained streams are not supported; stop at the first boundary.
+        Err(SymphoniaError::ResetRequired) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Packets in each Opus fixture: 20 ms each, 100 ms of silence followed by
+    /// a tone at half scale. Encoded by libopus, which asks for a 312-sample
+    /// pre-skip.
+    const OPUS_PACKETS: usize = 20;
+    const OPUS_FRAME: usize = 960;
+    const PRE_SKIP: usize = 312;
+    const TONE_START: usize = 4_800;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/audio/testdata")
+            .join(name)
+    }
+
+    fn channel(audio: &DecodedAudio, index: usize) -> Vec<f32> {
+        audio
+            .samples
+            .iter()
+            .skip(index)
+            .step_by(audio.channels as usize)
+            .copied()
+            .collect()
+    }
+
+    fn onset(samples: &[f32]) -> usize {
+        samples.iter().position(|s| s.abs() > 0.1).unwrap()
+    }
+
+    /// Tone frequency estimated from zero crossings after the onset.
+    fn frequency(samples: &[f32], rate: u32) -> f64 {
+        let tone = &samples[TONE_START + 480..];
+        let crossings = tone
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        crossings as f64 * rate as f64 / (2.0 * tone.len() as f64)
+    }
+
+    #[test]
+    fn decodes_ogg_opus_and_trims_pre_skip() {
+        // Mono, 440 Hz.
+        let audio = decode(&fixture("tone-mono.opus")).unwrap();
+        assert_eq!(audio.sample_rate, 48_000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.samples.len(), OPUS_PACKETS * OPUS_FRAME - PRE_SKIP);
+        // Without trimming the tone would start 312 samples late.
+        assert!(onset(&audio.samples).abs_diff(TONE_START) < 50);
+        assert!((frequency(&audio.samples, audio.sample_rate) - 440.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn decodes_media_recorder_webm_opus() {
+        // Stereo, 440 Hz left and 660 Hz right, muxed like Chrome's
+        // MediaRecorder: unknown-size segment and cluster, no cues.
+        let audio = decode(&fixture("tone-stereo.webm")).unwrap();
+        assert_eq!(audio.sample_rate, 48_000);
+        assert_eq!(audio.channels, 2);
+        assert_eq!(
+            audio.samples.len(),
+            (OPUS_PACKETS * OPUS_FRAME - PRE_SKIP) * 2
+        );
+        for (index, expected) in [(0, 440.0), (1, 660.0)] {
+            let samples = channel(&audio, index);
+            assert!(onset(&samples).abs_diff(TONE_START) < 50);
+            assert!((frequency(&samples, audio.sample_rate) - expected).abs() < 5.0);
+        }
+    }
+
+    #[test]
+    fn decodes_mp3_through_symphonia() {
+        let jfk = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../../packages/whisper-wrapper/whisper.cpp/samples/jfk.mp3");
+        let audio = decode(&jfk).unwrap();
+        assert_eq!(audio.sample_rate, 16_000);
+        assert_eq!(audio.channels, 1);
+        let seconds = audio.samples.len() as f64 / audio.sample_rate as f64;
+        assert!((11.0..11.2).contains(&seconds), "{seconds}");
+    }
+}
@@ -0,0 +1,559 @@
+//! Audio decoding and sample-rate conversion for the transcription pipeline.
+//!
+//! Everything is converted to mono `f32` in `[-1.0, 1.0]` at a caller-chosen
+//! rate (normally the 16 kHz whisper expects), regardless of what the renderer
+//! recorded or which file the user imported.
+
+mod container;
+
+use std::f64::consts::PI;
+use std::io::Read;
+use std::path::Path;
+
+/// Zero crossings of the sinc kernel on each side of the interpolation point.
+const SINC_ZERO_CROSSINGS: f64 = 16.0;
+
+/// Keep the passband slightly below Nyquist so the window's transition band
+/// does not fold back into the audible range.
+const CUTOFF_MARGIN: f64 = 0.95;
+
+/// File extensions accepted by `decode_file`.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "wav", "flac", "ogg", "oga", "opus", "mp3", "m4a", "mp4", "aac", "webm", "mka", "mkv",
+];
+
+/// Interleaved PCM as read from a container, before any conversion.
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl DecodedAudio {
+    /// Downmix and resample to mono at `target_rate`.
+    pub fn into_mono(self, target_rate: u32) -> Vec<f32> {
+        let mono = downmix_to_mono(&self.samples, self.channels);
+        resample(&mono, self.sample_rate, target_rate)
+    }
+}
+
+/// Decode a WAV file (integer PCM up to 32-bit, or 32-bit float).
+pub fn decode_wav(path: &Path) -> Result<DecodedAudio, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    decode_wav_from_reader(std::io::BufReader::new(file))
+}
+
+/// Decode WAV data from any reader.
+pub fn decode_wav_from_reader<R: Read>(reader: R) -> Result<DecodedAudio, String> {
+    let mut reader = hound::WavReader::new(reader).map_err(|e| e.to_string())?;
+    let spec = reader.spec();
+    if spec.channels == 0 {
+        return Err("WAV file has no channels".to_string());
+    }
+
+    let samples: Vec<f32> = match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, 32) => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?,
+        (hound::SampleFormat::Int, bits @ 1..=32) => {
+            let scale = (1i64 << (bits - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|v| v as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?
+        }
+        (format, bits) => {
+            return Err(format!(
+                "Unsupported WAV sample format: {format:?} {bits}-bit"
+            ))
+        }
+    };
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+    })
+}
+
+/// Decode any supported audio file. WAV goes through `hound`; everything
+/// else (and WAV variants `hound` rejects) through symphonia.
+pub fn decode_file(path: &Path) -> Result<DecodedAudio, String> {
+    let is_wav = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+    if is_wav {
+        match decode_wav(path) {
+            Ok(audio) => return Ok(audio),
+            Err(e) => log::debug!("hound could not read {}: {e}", path.display()),
+        }
+    }
+    container::decode(path)
+}
+
+/// Decode an audio file and convert it to mono `f32` at `target_rate`.
+pub fn load_mono(path: &Path, target_rate: u32) -> Result<Vec<f32>, String> {
+    Ok(decode_file(path)?.into_mono(target_rate))
+}
+
+/// Average interleaved channels down to a single channel.
+pub fn downmix_to_mono(interleaved: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    if channels == 1 {
+        return interleaved.to_vec();
+    }
+    interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Band-limited resampling with a Blackman-windowed sinc kernel.
+///
+/// When downsampling, the kernel's cutoff is lowered to the target Nyquist
+/// frequency so content above it is attenuated instead of aliasing.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    let mut resampler = Resampler::new(from_rate, to_rate);
+    let mut output = resampler.process(samples);
+    output.extend(resampler.finish());
+    output
+}
+
+/// [`resample`] over audio that arrives in chunks. Output near the end of a
+/// chunk waits for the input after it, so the result is the same as
+/// resampling everything at once, without seams at chunk boundaries.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    /// `None` when the rates match and samples pass through unchanged.
+    kernel: Option<Kernel>,
+    /// Input still needed by the kernel; `input[0]` is input sample
+    /// number `offset`.
+    input: Vec<f32>,
+    offset: usize,
+    /// Input samples received in total.
+    received: usize,
+    /// Index of the next output sample.
+    next: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Kernel {
+    /// Output sample `n` sits at input position `n * step / phases`, with
+    /// the two rates reduced by their greatest common divisor.
+    step: usize,
+    phases: usize,
+    /// Cutoff as a fraction of the input Nyquist frequency.
+    cutoff: f64,
+    half_width: usize,
+    /// Weights of the `2 * half_width` taps for each phase, or `None` when
+    /// the rates share so little that the table would be too large.
+    weights: Option<Vec<f64>>,
+}
+
+/// Largest weight table worth precomputing, in taps.
+const MAX_KERNEL_TABLE: usize = 1 << 20;
+
+impl Kernel {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        let divisor = gcd(from_rate as usize, to_rate as usize);
+        let (step, phases) = (from_rate as usize / divisor, to_rate as usize / divisor);
+        let cutoff = (phases as f64 / step as f64).min(1.0) * CUTOFF_MARGIN;
+        let half_width = (SINC_ZERO_CROSSINGS / cutoff).ceil() as usize;
+        let mut kernel = Self {
+            step,
+            phases,
+            cutoff,
+            half_width,
+            weights: None,
+        };
+        let taps = 2 * half_width;
+        if phases * taps <= MAX_KERNEL_TABLE {
+            let weights = (0..phases)
+                .flat_map(|phase| (0..taps).map(move |tap| (phase, tap)))
+                .map(|(phase, tap)| kernel.compute_weight(phase, tap))
+                .collect();
+            kernel.weights = Some(weights);
+        }
+        kernel
+    }
+
+    /// Output samples per input sample.
+    fn ratio(&self) -> f64 {
+        self.phases as f64 / self.step as f64
+    }
+
+    /// Input sample at or before output `n`, and the phase of `n` past it.
+    fn position(&self, n: usize) -> (usize, usize) {
+        let t = n * self.step;
+        (t / self.phases, t % self.phases)
+    }
+
+    /// Weight of input sample `center - half_width + 1 + tap`.
+    fn weight(&self, phase: usize, tap: usize) -> f64 {
+        match &self.weights {
+            Some(weights) => weights[phase * 2 * self.half_width + tap],
+            None => self.compute_weight(phase, tap),
+        }
+    }
+
+    fn compute_weight(&self, phase: usize, tap: usize) -> f64 {
+        let half_width = self.half_width as f64;
+        let x = phase as f64 / self.phases as f64 + half_width - 1.0 - tap as f64;
+        self.cutoff * sinc(self.cutoff * x) * blackman(x / half_width)
+    }
+
+    /// Interpolate output `n` from `input`, which starts at input sample
+    /// `offset` of the `received` so far, truncating the kernel at the edges
+    /// of the stream.
+    fn sample_at(&self, input: &[f32], offset: usize, received: usize, n: usize) -> f32 {
+        let (center, phase) = self.position(n);
+        let first_tap = center + 1;
+        let first = first_tap.saturating_sub(self.half_width);
+        let end = (center + self.half_width).min(received - 1);
+
+        let mut acc = 0.0f64;
+        let mut weight_sum = 0.0f64;
+        for j in first..=end {
+            let w = self.weight(phase, j + self.half_width - first_tap);
+            acc += input[j - offset] as f64 * w;
+            weight_sum += w;
+        }
+        // Normalising by the summed weights keeps unity DC gain, including
+        // at the edges where the kernel is truncated.
+        if weight_sum.abs() > f64::EPSILON {
+            (acc / weight_sum) as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        let kernel = (from_rate != to_rate && from_rate != 0 && to_rate != 0)
+            .then(|| Kernel::new(from_rate, to_rate));
+        Self {
+            kernel,
+            input: Vec::new(),
+            offset: 0,
+            received: 0,
+            next: 0,
+        }
+    }
+
+    /// Feed the next chunk, returning the output it completes.
+    pub fn process(&mut self, chunk: &[f32]) -> Vec<f32> {
+        let Some(kernel) = &self.kernel else {
+            return chunk.to_vec();
+        };
+        self.input.extend_from_slice(chunk);
+        self.received += chunk.len();
+
+        let mut output = Vec::new();
+        loop {
+            // Wait until the kernel's right half has arrived.
+            let (center, _) = kernel.position(self.next);
+            if center + kernel.half_width >= self.received {
+                break;
+            }
+            output.push(kernel.sample_at(&self.input, self.offset, self.received, self.next));
+            self.next += 1;
+        }
+
+        // Keep only what the next output's left half still reaches.
+        let (center, _) = kernel.position(self.next);
+        let keep_from = (center + 1).saturating_sub(kernel.half_width);
+        if keep_from > self.offset {
+            let drop = (keep_from - self.offset).min(self.input.len());
+            self.input.drain(..drop);
+            self.offset += drop;
+        }
+        output
+    }
+
+    /// Flush the output held back for input that will not come, and reset
+    /// for a new stream.
+    pub fn finish(&mut self) -> Vec<f32> {
+        let mut output = Vec::new();
+        if let Some(kernel) = &self.kernel {
+            let out_len = (self.received as f64 * kernel.ratio()).round() as usize;
+            while self.next < out_len {
+                output.push(kernel.sample_at(&self.input, self.offset, self.received, self.next));
+                self.next += 1;
+            }
+        }
+        self.input.clear();
+        self.offset = 0;
+        self.received = 0;
+        self.next = 0;
+        output
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over `x` in `[-1, 1]`; zero outside.
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    let n = (x + 1.0) / 2.0;
+    0.42 - 0.5 * (2.0 * PI * n).cos() + 0.08 * (4.0 * PI * n).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn sine(freq: f64, rate: u32, seconds: f64, amplitude: f32) -> Vec<f32> {
+        let n = (rate as f64 * seconds) as usize;
+        (0..n)
+            .map(|i| amplitude * (2.0 * PI * freq * i as f64 / rate as f64).sin() as f32)
+            .collect()
+    }
+
+    /// Deterministic white noise in `[-1, 1)` from a 64-bit LCG.
+    fn noise(len: usize, seed: u64) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                ((state >> 40) as f32 / (1u64 << 23) as f32) - 1.0
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// Skip the kernel's edge effects when measuring steady-state signals.
+    fn interior(samples: &[f32]) -> &[f32] {
+        let margin = samples.len() / 10;
+        &samples[margin..samples.len() - margin]
+    }
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count()
+    }
+
+    fn write_wav(spec: hound::WavSpec, interleaved: &[f32]) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+            for &s in interleaved {
+                match spec.sample_format {
+                    hound::SampleFormat::Float => writer.write_sample(s).unwrap(),
+                    hound::SampleFormat::Int => {
+                        let max = ((1i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+                        writer.write_sample((s * max).round() as i32).unwrap()
+                    }
+                }
+            }
+            writer.finalize().unwrap();
+        }
+        cursor.into_inner()
+    }
+
+    fn spec(channels: u16, rate: u32, bits: u16, format: hound::SampleFormat) -> hound::WavSpec {
+        hound::WavSpec {
+            channels,
+            sample_rate: rate,
+            bits_per_sample: bits,
+            sample_format: format,
+        }
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() <= tolerance, "sample {i}: {a} vs {e}");
+        }
+    }
+
+    #[test]
+    fn decodes_pcm16() {
+        let signal = sine(440.0, 16_000, 0.25, 0.5);
+        let bytes = write_wav(spec(1, 16_000, 16, hound::SampleFormat::Int), &signal);
+        let decoded = decode_wav_from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoded.sample_rate, 16_000);
+        assert_eq!(decoded.channels, 1);
+        assert_close(&decoded.samples, &signal, 1.0 / 16_000.0);
+    }
+
+    #[test]
+    fn decodes_pcm24() {
+        let signal = sine(1_000.0, 44_100, 0.1, 0.8);
+        let bytes = write_wav(spec(1, 44_100, 24, hound::SampleFormat::Int), &signal);
+        let decoded = decode_wav_from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoded.sample_rate, 44_100);
+        assert_close(&decoded.samples, &signal, 1e-6);
+    }
+
+    #[test]
+    fn decodes_float32() {
+        let signal = noise(4_800, 7);
+        let bytes = write_wav(spec(1, 48_000, 32, hound::SampleFormat::Float), &signal);
+        let decoded = decode_wav_from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoded.samples, signal);
+    }
+
+    #[test]
+    fn rejects_non_wav_data() {
+        assert!(decode_wav_from_reader(Cursor::new(b"not a wav file".to_vec())).is_err());
+    }
+
+    #[test]
+    fn downmixes_stereo_by_averaging() {
+        let interleaved = [1.0, 0.0, 0.5, 0.5, -1.0, 1.0];
+        assert_eq!(downmix_to_mono(&interleaved, 2), vec![0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn downmixes_multichannel() {
+        let left = sine(300.0, 16_000, 0.1, 0.6);
+        let interleaved: Vec<f32> = left.iter().flat_map(|&s| [s, s, s, s, s, s]).collect();
+        assert_close(&downmix_to_mono(&interleaved, 6), &left, 1e-6);
+    }
+
+    #[test]
+    fn downmix_drops_incomplete_trailing_frame() {
+        assert_eq!(downmix_to_mono(&[0.2, 0.4, 0.6], 2), vec![0.3]);
+    }
+
+    #[test]
+    fn resample_is_identity_for_equal_rates() {
+        let signal = noise(1_000, 3);
+        assert_eq!(resample(&signal, 16_000, 16_000), signal);
+    }
+
+    #[test]
+    fn chunked_resampling_matches_one_pass() {
+        let input = noise(48_000, 11);
+        let expected = resample(&input, 48_000, 16_000);
+
+        let mut resampler = Resampler::new(48_000, 16_000);
+        let mut output = Vec::new();
+        let mut rest = input.as_slice();
+        for size in [1, 7, 480, 4_096, 100, 2_048].into_iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at(size.min(rest.len()));
+            output.extend(resampler.process(chunk));
+            rest = tail;
+        }
+        output.extend(resampler.finish());
+        assert_eq!(output, expected);
+
+        // Finishing resets the resampler for the next stream.
+        let mut output = resampler.process(&input);
+        output.extend(resampler.finish());
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn weight_table_matches_direct_weights() {
+        let input = noise(44_100, 5);
+        for (from, to) in [(48_000, 16_000), (44_100, 16_000), (16_000, 48_000)] {
+            let mut resampler = Resampler::new(from, to);
+            let kernel = resampler.kernel.as_ref().unwrap();
+            assert!(kernel.weights.is_some(), "{from} -> {to}");
+            let expected = resample(&input, from, to);
+
+            resampler.kernel.as_mut().unwrap().weights = None;
+            let mut output = resampler.process(&input);
+            output.extend(resampler.finish());
+            assert_eq!(output, expected, "{from} -> {to}");
+        }
+
+        // Rates with no common factor would need a table per output sample.
+        let kernel = Kernel::new(44_101, 16_000);
+        assert_eq!(kernel.phases, 16_000);
+        assert!(kernel.weights.is_none());
+    }
+
+    #[test]
+    fn downsample_preserves_tone() {
+        let input = sine(440.0, 48_000, 1.0, 0.5);
+        let output = resample(&input, 48_000, 16_000);
+        assert_eq!(output.len(), 16_000);
+        let expected = sine(440.0, 16_000, 1.0, 0.5);
+        assert_close(interior(&output), interior(&expected), 0.01);
+    }
+
+    #[test]
+    fn downsample_from_cd_rate() {
+        let input = sine(1_000.0, 44_100, 0.5, 0.5);
+        let output = resample(&input, 44_100, 16_000);
+        assert_eq!(output.len(), 8_000);
+        // 1 kHz over 0.5 s crosses zero ~1000 times.
+        let crossings = zero_crossings(&output) as i64;
+        assert!((crossings - 1_000).abs() <= 2, "{crossings} crossings");
+        assert!((rms(interior(&output)) - 0.5 / 2f32.sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn upsample_preserves_tone() {
+        let input = sine(500.0, 8_000, 1.0, 0.5);
+        let output = resample(&input, 8_000, 16_000);
+        assert_eq!(output.len(), 16_000);
+        let expected = sine(500.0, 16_000, 1.0, 0.5);
+        assert_close(interior(&output), interior(&expected), 0.01);
+    }
+
+    #[test]
+    fn downsample_attenuates_content_above_target_nyquist() {
+        // 12 kHz would alias to 4 kHz at 16 kHz without filtering.
+        let input = sine(12_000.0, 48_000, 0.5, 0.8);
+        let output = resample(&input, 48_000, 16_000);
+        assert!(rms(interior(&output)) < 0.01);
+    }
+
+    #[test]
+    fn downsample_band_limits_white_noise() {
+        // Only the lower third of the noise spectrum survives 48 kHz -> 16 kHz,
+        // so roughly sqrt(1/3) of the RMS should remain.
+        let input = noise(48_000, 42);
+        let output = resample(&input, 48_000, 16_000);
+        let ratio = rms(interior(&output)) / rms(&input);
+        assert!((0.5..0.65).contains(&ratio), "rms ratio {ratio}");
+    }
+
+    #[test]
+    fn converts_stereo_48k_wav_to_16k_mono() {
+        let tone = sine(440.0, 48_000, 0.5, 0.5);
+        let interleaved: Vec<f32> = tone.iter().flat_map(|&s| [s, s]).collect();
+        let bytes = write_wav(spec(2, 48_000, 16, hound::SampleFormat::Int), &interleaved);
+        let mono = decode_wav_from_reader(Cursor::new(bytes))
+            .unwrap()
+            .into_mono(16_000);
+        assert_eq!(mono.len(), 8_000);
+        let expected = sine(440.0, 16_000, 0.5, 0.5);
+        assert_close(interior(&mono), interior(&expected), 0.01);
+    }
+}
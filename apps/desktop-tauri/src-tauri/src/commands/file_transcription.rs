@@ -0,0 +1,287 @@
+use crate::audio;
+use crate::db::Transcription;
+use crate::pipeline::cancel::{CancelToken, CANCELLED};
+use crate::pipeline::{self, PipelineConfig, PipelineStage};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{Emitter, State};
+use uuid::Uuid;
+
+type AppStateGuard<'a> = State<'a, AppState>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileTranscriptionStage {
+    Queued,
+    Decoding,
+    Transcribing,
+    Formatting,
+    Saving,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl From<PipelineStage> for FileTranscriptionStage {
+    fn from(stage: PipelineStage) -> Self {
+        match stage {
+            PipelineStage::Decoding => Self::Decoding,
+            PipelineStage::Transcribing => Self::Transcribing,
+            PipelineStage::Formatting => Self::Formatting,
+        }
+    }
+}
+
+/// Payload of the `file-transcription-progress` event.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTranscriptionProgress {
+    pub job_id: String,
+    pub file_path: String,
+    /// Zero-based position of this file within the job.
+    pub file_index: usize,
+    pub file_count: usize,
+    pub stage: FileTranscriptionStage,
+    /// Percentage of the stage done, when known.
+    pub percent: Option<u8>,
+    pub transcription_id: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Per-file result of `transcribe_files`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTranscriptionOutcome {
+    pub file_path: String,
+    pub transcription: Option<Transcription>,
+    pub error: Option<String>,
+}
+
+/// File extensions the importer understands, for the renderer's file dialog.
+#[tauri::command]
+pub fn get_supported_audio_extensions() -> Vec<String> {
+    audio::SUPPORTED_EXTENSIONS
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+/// Transcribe an existing audio file and save it to history.
+#[tauri::command]
+pub async fn transcribe_file(
+    state: AppStateGuard<'_>,
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<Transcription, String> {
+    let job = FileJob::start(&state)?;
+    let progress = ProgressReporter {
+        app: &app,
+        job_id: &job.id,
+        file_path: &path,
+        file_index: 0,
+        file_count: 1,
+    };
+    run_file(&state, &progress, &job.cancel).await
+}
+
+/// Transcribe several files one after another. A failing file is reported in
+/// its outcome and does not stop the rest of the batch; cancelling the job
+/// does.
+#[tauri::command]
+pub async fn transcribe_files(
+    state: AppStateGuard<'_>,
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+) -> Result<Vec<FileTranscriptionOutcome>, String> {
+    let job = FileJob::start(&state)?;
+    let file_count = paths.len();
+
+    for (file_index, path) in paths.iter().enumerate() {
+        ProgressReporter {
+            app: &app,
+            job_id: &job.id,
+            file_path: path,
+            file_index,
+            file_count,
+        }
+        .emit(FileTranscriptionStage::Queued);
+    }
+
+    let mut outcomes = Vec::with_capacity(file_count);
+    for (file_index, path) in paths.into_iter().enumerate() {
+        let progress = ProgressReporter {
+            app: &app,
+            job_id: &job.id,
+            file_path: &path,
+            file_index,
+            file_count,
+        };
+        let outcome = match run_file(&state, &progress, &job.cancel).await {
+            Ok(transcription) => FileTranscriptionOutcome {
+                file_path: path.clone(),
+                transcription: Some(transcription),
+                error: None,
+            },
+            Err(e) => FileTranscriptionOutcome {
+                file_path: path.clone(),
+                transcription: None,
+                error: Some(e),
+            },
+        };
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+/// Cancel file transcription job `job_id`. The file in progress stops with
+/// "Transcription cancelled" and the rest of a batch is skipped.
+#[tauri::command]
+pub fn cancel_file_transcription(state: AppStateGuard, job_id: String) -> Result<(), String> {
+    state
+        .file_jobs()?
+        .get(&job_id)
+        .ok_or_else(|| format!("No running file transcription job {job_id}"))?
+        .cancel();
+    Ok(())
+}
+
+/// A running job, cancellable through `cancel_file_transcription` until
+/// dropped.
+struct FileJob<'a> {
+    state: &'a AppState,
+    id: String,
+    cancel: CancelToken,
+}
+
+impl<'a> FileJob<'a> {
+    fn start(state: &'a AppState) -> Result<Self, String> {
+        let id = Uuid::new_v4().to_string();
+        let cancel = CancelToken::default();
+        state.file_jobs()?.insert(id.clone(), cancel.clone());
+        Ok(Self { state, id, cancel })
+    }
+}
+
+impl Drop for FileJob<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = self.state.file_jobs() {
+            jobs.remove(&self.id);
+        }
+    }
+}
+
+struct ProgressReporter<'a> {
+    app: &'a tauri::AppHandle,
+    job_id: &'a str,
+    file_path: &'a str,
+    file_index: usize,
+    file_count: usize,
+}
+
+impl ProgressReporter<'_> {
+    fn emit(&self, stage: FileTranscriptionStage) {
+        self.emit_with(stage, None, None, None);
+    }
+
+    fn emit_progress(&self, stage: FileTranscriptionStage, percent: Option<u8>) {
+        self.emit_with(stage, percent, None, None);
+    }
+
+    fn emit_with(
+        &self,
+        stage: FileTranscriptionStage,
+        percent: Option<u8>,
+        transcription_id: Option<i64>,
+        error: Option<String>,
+    ) {
+        let _ = self.app.emit(
+            "file-transcription-progress",
+            FileTranscriptionProgress {
+                job_id: self.job_id.to_string(),
+                file_path: self.file_path.to_string(),
+                file_index: self.file_index,
+                file_count: self.file_count,
+                stage,
+                percent,
+                transcription_id,
+                error,
+            },
+        );
+    }
+}
+
+/// Run one file through the shared pipeline and save it with
+/// `meta.source = "file"`, emitting progress along the way.
+async fn run_file(
+    state: &AppState,
+    progress: &ProgressReporter<'_>,
+    cancel: &CancelToken,
+) -> Result<Transcription, String> {
+    let result = process_and_save(state, progress, cancel).await;
+    match &result {
+        Ok(t) => progress.emit_with(FileTranscriptionStage::Completed, None, Some(t.id), None),
+        Err(e) if e == CANCELLED => {
+            progress.emit_with(FileTranscriptionStage::Cancelled, None, None, Some(e.clone()));
+        }
+        Err(e) => {
+            log::error!("File transcription failed for {}: {e}", progress.file_path);
+            progress.emit_with(FileTranscriptionStage::Failed, None, None, Some(e.clone()));
+        }
+    }
+    result
+}
+
+async fn process_and_save(
+    state: &AppState,
+    progress: &ProgressReporter<'_>,
+    cancel: &CancelToken,
+) -> Result<Transcription, String> {
+    cancel.check()?;
+    let path = Path::new(progress.file_path);
+    if !path.is_file() {
+        return Err(format!("File not found: {}", progress.file_path));
+    }
+
+    let config = PipelineConfig::from_state(state)?;
+
+    let output = pipeline::process_file(
+        &config,
+        progress.file_path,
+        cancel,
+        |stage, percent| progress.emit_progress(stage.into(), percent),
+    )
+    .await?;
+
+    progress.emit(FileTranscriptionStage::Saving);
+    let meta = serde_json::json!({
+        "source": "file",
+        "fileName": path.file_name().map(|n| n.to_string_lossy().into_owned()),
+        "jobId": progress.job_id,
+        "languageProbability": output.language_probability,
+        "speechSegments": output.speech_segments,
+        "filteredSegments": output.filtered_segments,
+        "lowConfidenceSpans": output.low_confidence_spans,
+        "firedRules": output.fired_rules,
+    });
+    let db = state.db()?;
+    let id = db
+        .transaction(|| {
+            let id = db.create_transcription(
+                &output.text,
+                output.language.as_deref(),
+                Some(progress.file_path),
+                output.confidence,
+                Some(output.duration_secs),
+                Some("whisper-local"),
+                output.formatting_model.as_deref(),
+                Some(&meta),
+            )?;
+            db.save_transcription_segments(id, &output.segments)?;
+            Ok(id)
+        })
+        .map_err(|e| e.to_string())?;
+    db.get_transcription(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Saved transcription not found".to_string())
+}
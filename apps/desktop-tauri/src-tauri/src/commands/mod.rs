@@ -1,7 +1,10 @@
 pub mod app;
+pub mod file_transcription;
 pub mod models;
 pub mod onboarding;
 pub mod recording;
 pub mod settings;
+pub mod text_rules;
 pub mod transcriptions;
+pub mod vocabulary;
 pub mod widget;
@@ -1,21 +1,70 @@
-use crate::db::Model;
+use crate::db::{Database, Model, ModelStatus};
+use crate::model_files::catalog::{self, CatalogModel, CATALOG_PROVIDER};
+use crate::model_files::import::{self, IMPORT_PROVIDER};
+use crate::model_files::reconcile::{self, ModelFilesReport};
+use crate::model_files::verify::{self, Outcome};
+use crate::model_files::{self, download};
 use crate::state::AppState;
-use std::sync::Mutex;
-use tauri::State;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
 
-type AppStateGuard<'a> = State<'a, Mutex<AppState>>;
+type AppStateGuard<'a> = State<'a, AppState>;
+
+/// Minimum time between `model-download-progress` events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadModelOptions {
+    pub id: String,
+    pub provider: String,
+    /// The remaining fields default to the catalog entry for `id`.
+    pub name: Option<String>,
+    pub url: Option<String>,
+    /// Expected hex SHA-1 of the file.
+    pub checksum: Option<String>,
+}
+
+/// Payload of the `model-download-progress` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelDownloadProgress {
+    pub model_id: String,
+    pub provider: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+}
+
+/// Payload of the `model-corrupt` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorruptModel {
+    pub model_id: String,
+    pub provider: String,
+    pub local_path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelVerificationReport {
+    /// Number of files hashed.
+    pub checked: usize,
+    pub corrupt: Vec<CorruptModel>,
+}
 
 #[tauri::command]
 pub fn get_models(state: AppStateGuard) -> Result<Vec<Model>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.db.get_models().map_err(|e| e.to_string())
+    let db = state.db()?;
+    db.get_models().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn get_selected_model(state: AppStateGuard) -> Result<Option<String>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
     Ok(state
-        .settings
+        .settings()?
         .model_providers_config
         .as_ref()
         .and_then(|c| c.default_speech_model.clone()))
@@ -26,37 +75,265 @@ pub fn select_model(
     state: AppStateGuard,
     model_id: String,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    let config = state
-        .settings
-        .model_providers_config
-        .get_or_insert_with(|| crate::state::ModelProvidersConfig {
-            ollama: None,
-            default_speech_model: None,
-            default_language_model: None,
-        });
-    config.default_speech_model = Some(model_id);
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+    state.update_settings(|settings| {
+        let config = settings
+            .model_providers_config
+            .get_or_insert_with(|| crate::state::ModelProvidersConfig {
+                ollama: None,
+                default_speech_model: None,
+                default_language_model: None,
+            });
+        config.default_speech_model = Some(model_id);
+    })?;
+    state.preload_speech_model()
 }
 
 #[tauri::command]
 pub fn save_model(state: AppStateGuard, model: Model) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.db.save_model(&model).map_err(|e| e.to_string())
+    let db = state.db()?;
+    db.save_model(&model).map_err(|e| e.to_string())
 }
 
+/// Delete a model along with its file in the models directory. Catalog
+/// models stay listed for download again. If it was the selected speech
+/// model, the most recently downloaded one takes its place.
 #[tauri::command]
-pub fn delete_model(
-    state: AppStateGuard,
+pub async fn delete_model(
+    state: AppStateGuard<'_>,
     id: String,
     provider: String,
 ) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state
-        .db
-        .delete_model(&id, &provider)
-        .map_err(|e| e.to_string())
+    let local_path = state
+        .db()?
+        .get_model(&id, &provider)
+        .map_err(|e| e.to_string())?
+        .and_then(|m| m.local_path);
+    if let Some(local_path) = local_path {
+        // Eviction waits for any transcription using the model to finish.
+        let models = state.models.clone();
+        tokio::task::spawn_blocking(move || {
+            let path = Path::new(&local_path);
+            models.evict(path);
+            reconcile::remove_model_file(path, &model_files::models_dir())
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+    }
+    forget_model(&*state.db()?, &id, &provider)?;
+    state.fall_back_speech_model(&id)
+}
+
+/// Compare the models directory with the `models` table.
+#[tauri::command]
+pub fn scan_model_files(state: AppStateGuard) -> Result<ModelFilesReport, String> {
+    let models = state.db()?.get_models().map_err(|e| e.to_string())?;
+    Ok(reconcile::scan(&models, &model_files::models_dir()))
+}
+
+/// Delete orphaned model files and forget models whose file is missing.
+/// Returns what was fixed.
+#[tauri::command]
+pub fn reconcile_model_files(state: AppStateGuard) -> Result<ModelFilesReport, String> {
+    let report = scan_model_files(state.clone())?;
+    let dir = model_files::models_dir();
+    for file in &report.orphan_files {
+        reconcile::remove_model_file(Path::new(file), &dir)?;
+    }
+    {
+        let db = state.db()?;
+        for missing in &report.missing_files {
+            forget_model(&db, &missing.id, &missing.provider)?;
+        }
+    }
+    for missing in &report.missing_files {
+        state.fall_back_speech_model(&missing.id)?;
+    }
+    Ok(report)
+}
+
+/// Drop the download record of a model: catalog rows stay listed, others
+/// are removed.
+fn forget_model(db: &Database, id: &str, provider: &str) -> Result<(), String> {
+    let entry = catalog::find(id).filter(|_| provider == CATALOG_PROVIDER);
+    match entry {
+        Some(entry) => db.clear_model_download(id, provider, entry.checksum.as_deref()),
+        None => db.delete_model(id, provider),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Download a speech model into the models directory and record it. An
+/// interrupted download resumes where it stopped when retried, and a file
+/// whose SHA-1 does not match `checksum` is discarded.
+#[tauri::command]
+pub async fn download_model(
+    state: AppStateGuard<'_>,
+    app: tauri::AppHandle,
+    options: DownloadModelOptions,
+) -> Result<Model, String> {
+    let entry = catalog::find(&options.id).filter(|_| options.provider == CATALOG_PROVIDER);
+    let url = options
+        .url
+        .as_deref()
+        .or(entry.map(|e| e.url.as_str()))
+        .ok_or_else(|| format!("No download URL for model {}", options.id))?;
+    let checksum = options
+        .checksum
+        .as_deref()
+        .or(entry.and_then(|e| e.checksum.as_deref()));
+    if let (None, Some(reason)) = (checksum, entry.and_then(|e| e.unverified.as_deref())) {
+        log::warn!("Downloading model {} unverified: {reason}", options.id);
+    }
+    let url = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    let file_name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(model_files::sanitize_file_name)
+        .or_else(|| model_files::sanitize_file_name(&format!("{}.bin", options.id)))
+        .ok_or_else(|| format!("Invalid model id: {}", options.id))?;
+    let dest = model_files::models_dir().join(file_name);
+
+    let mut last_emit: Option<Instant> = None;
+    let on_progress = |downloaded_bytes, total_bytes| {
+        let done = total_bytes == Some(downloaded_bytes);
+        if !done && last_emit.is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        last_emit = Some(Instant::now());
+        let progress = ModelDownloadProgress {
+            model_id: options.id.clone(),
+            provider: options.provider.clone(),
+            downloaded_bytes,
+            total_bytes,
+        };
+        let _ = app.emit("model-download-progress", &progress);
+    };
+    let file = download::download(
+        &reqwest::Client::new(),
+        url.as_str(),
+        &dest,
+        checksum,
+        on_progress,
+    )
+    .await?;
+
+    let mut model = entry
+        .map(CatalogModel::to_model)
+        .unwrap_or_else(|| Model::speech(&options.id, &options.provider, &options.id));
+    if let Some(name) = options.name {
+        model.name = name;
+    }
+    model.local_path = Some(file.path.display().to_string());
+    model.size_bytes = Some(file.size_bytes as i64);
+    model.checksum = Some(file.checksum);
+    model.downloaded_at = Some(unix_now());
+    let db = state.db()?;
+    save_verified_model(&db, &model)?;
+    db.get_model(&model.id, &model.provider)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Model {} was not saved", model.id))
+}
+
+/// Import a whisper model in ggml format from `path` and register it under
+/// the `local-import` provider. The file is hard-linked into the models
+/// directory when possible and copied otherwise.
+#[tauri::command]
+pub async fn import_model(
+    state: AppStateGuard<'_>,
+    path: String,
+    name: Option<String>,
+) -> Result<Model, String> {
+    let source = std::path::PathBuf::from(path);
+    let dir = model_files::models_dir();
+    let file = tokio::task::spawn_blocking(move || import::import(&source, &dir))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let id = file
+        .path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let model = Model {
+        size: Some(file.header.size_name().to_string()),
+        description: Some(file.header.describe()),
+        local_path: Some(file.path.display().to_string()),
+        size_bytes: Some(file.size_bytes as i64),
+        checksum: Some(file.checksum),
+        downloaded_at: Some(unix_now()),
+        ..Model::speech(&id, IMPORT_PROVIDER, name.as_deref().unwrap_or(&id))
+    };
+    let db = state.db()?;
+    save_verified_model(&db, &model)?;
+    db.get_model(&model.id, &model.provider)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Model {} was not saved", model.id))
+}
+
+/// Save a model whose file was just hashed against its checksum.
+fn save_verified_model(db: &Database, model: &Model) -> Result<(), String> {
+    let local_path = model.local_path.as_deref().unwrap_or_default();
+    db.transaction(|| {
+        db.save_model(model)?;
+        db.set_model_status(&model.id, &model.provider, local_path, ModelStatus::Verified)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Re-hash every downloaded model against its checksum, emitting
+/// `model-corrupt` for each file that no longer matches.
+#[tauri::command]
+pub async fn verify_models(app: AppHandle) -> Result<ModelVerificationReport, String> {
+    tokio::task::spawn_blocking(move || verify_model_files(&app, true))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Check downloaded model files, all of them or only those modified since
+/// their last check, and record the outcome in `models.status`. Missing
+/// files are left to [`scan_model_files`].
+pub fn verify_model_files(app: &AppHandle, all: bool) -> Result<ModelVerificationReport, String> {
+    let state = app.state::<AppState>();
+    let models = state.db()?.get_models().map_err(|e| e.to_string())?;
+    let mut report = ModelVerificationReport::default();
+    for model in &models {
+        let outcome = match verify::check(model, all) {
+            Ok(Some(outcome)) => outcome,
+            Ok(None) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                log::warn!("Failed to verify model {}: {e}", model.id);
+                continue;
+            }
+        };
+        report.checked += 1;
+        let local_path = model.local_path.clone().unwrap_or_default();
+        let status = match outcome {
+            Outcome::Verified => ModelStatus::Verified,
+            Outcome::Corrupt(_) => ModelStatus::Corrupt,
+        };
+        state
+            .db()?
+            .set_model_status(&model.id, &model.provider, &local_path, status)
+            .map_err(|e| e.to_string())?;
+        if let Outcome::Corrupt(reason) = outcome {
+            log::warn!("Model {} is corrupt: {reason}", model.id);
+            let corrupt = CorruptModel {
+                model_id: model.id.clone(),
+                provider: model.provider.clone(),
+                local_path,
+                reason,
+            };
+            let _ = app.emit("model-corrupt", &corrupt);
+            report.corrupt.push(corrupt);
+        }
+    }
+    Ok(report)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
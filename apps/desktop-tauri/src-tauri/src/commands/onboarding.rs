@@ -1,8 +1,7 @@
 use crate::state::{AppState, OnboardingSettings};
-use std::sync::Mutex;
 use tauri::{Emitter, Manager, State};
 
-type AppStateGuard<'a> = State<'a, Mutex<AppState>>;
+type AppStateGuard<'a> = State<'a, AppState>;
 
 #[derive(serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,9 +11,8 @@ pub struct OnboardingCheckResult {
 
 #[tauri::command]
 pub fn check_needs_onboarding(state: AppStateGuard) -> Result<OnboardingCheckResult, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
     Ok(OnboardingCheckResult {
-        needed: state.needs_onboarding(),
+        needed: state.needs_onboarding()?,
     })
 }
 
@@ -23,18 +21,13 @@ pub fn complete_onboarding(
     state: AppStateGuard,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut state = state.lock().map_err(|e| e.to_string())?;
-        state.settings.onboarding = Some(OnboardingSettings {
+    state.update_settings(|settings| {
+        settings.onboarding = Some(OnboardingSettings {
             completed_version: 1,
             completed_at: chrono_now(),
             selected_model_type: "local".to_string(),
         });
-        state
-            .db
-            .save_settings(&state.settings)
-            .map_err(|e| e.to_string())?;
-    }
+    })?;
 
     // Close onboarding window and show main window
     if let Some(onboarding) = app.get_webview_window("onboarding") {
@@ -1,10 +1,14 @@
-use crate::state::{AppState, RecordingState};
+use crate::pipeline::cancel::CancelToken;
+use crate::pipeline::streaming::StreamingSession;
+use crate::pipeline::{self, PipelineConfig, PipelineOutput, PipelineStage};
+use crate::session::{RecordingState, SessionEvent, SessionTimeline};
+use crate::state::AppState;
+use crate::whisper::WHISPER_SAMPLE_RATE;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
 use tauri::{Emitter, State};
 use uuid::Uuid;
 
-type AppStateGuard<'a> = State<'a, Mutex<AppState>>;
+type AppStateGuard<'a> = State<'a, AppState>;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -13,6 +17,16 @@ pub struct RecordingStateUpdate {
     pub session_id: Option<String>,
 }
 
+/// Payload of the `transcription-progress` event.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionProgress {
+    pub session_id: String,
+    pub stage: PipelineStage,
+    /// Percentage of the stage done, when known.
+    pub percent: Option<u8>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessChunkOptions {
@@ -32,24 +46,31 @@ pub struct FinalizeSessionOptions {
 }
 
 /// Signal that the user wants to start recording.
-/// Transitions: Idle -> Recording.
+/// Transitions: Idle | Error | Cancelled -> Recording.
 #[tauri::command]
 pub fn signal_start(
     state: AppStateGuard<'_>,
     app: tauri::AppHandle,
 ) -> Result<RecordingStateUpdate, String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-
-    if state.recording_state != RecordingState::Idle {
-        return Err("Recording already in progress".to_string());
-    }
+    let mut session = state.session()?;
+    session.check_transition(SessionEvent::Start)?;
 
     let session_id = Uuid::new_v4().to_string();
-    state.recording_state = RecordingState::Recording;
-    state.active_session_id = Some(session_id.clone());
+    let config = PipelineConfig::from_state(&state)?;
+    let recording_state = session.apply(SessionEvent::Start)?;
+    let cancel = CancelToken::default();
+    session.streaming = Some(StreamingSession::start(
+        app.clone(),
+        session_id.clone(),
+        &config,
+        state.models.clone(),
+        cancel.clone(),
+    ));
+    session.active_session_id = Some(session_id.clone());
+    session.cancel = Some(cancel);
 
     let update = RecordingStateUpdate {
-        state: state.recording_state.clone(),
+        state: recording_state,
         session_id: Some(session_id),
     };
 
@@ -59,24 +80,48 @@ pub fn signal_start(
     Ok(update)
 }
 
+/// Pause recording; audio sent while paused is discarded.
+/// Transitions: Recording -> Paused.
+#[tauri::command]
+pub fn signal_pause(
+    state: AppStateGuard<'_>,
+    app: tauri::AppHandle,
+) -> Result<RecordingStateUpdate, String> {
+    apply_signal(&state, &app, SessionEvent::Pause)
+}
+
+/// Resume a paused recording.
+/// Transitions: Paused -> Recording.
+#[tauri::command]
+pub fn signal_resume(
+    state: AppStateGuard<'_>,
+    app: tauri::AppHandle,
+) -> Result<RecordingStateUpdate, String> {
+    apply_signal(&state, &app, SessionEvent::Resume)
+}
+
 /// Signal that the user wants to stop recording.
-/// Transitions: Recording -> Processing.
+/// Transitions: Recording | Paused -> Processing.
 #[tauri::command]
 pub fn signal_stop(
     state: AppStateGuard<'_>,
     app: tauri::AppHandle,
 ) -> Result<RecordingStateUpdate, String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-
-    if state.recording_state == RecordingState::Idle {
-        return Err("No recording in progress".to_string());
-    }
+    apply_signal(&state, &app, SessionEvent::Stop)
+}
 
-    state.recording_state = RecordingState::Processing;
+/// Apply `event` to the active session and notify all windows.
+fn apply_signal(
+    state: &AppState,
+    app: &tauri::AppHandle,
+    event: SessionEvent,
+) -> Result<RecordingStateUpdate, String> {
+    let mut session = state.session()?;
+    let recording_state = session.apply(event)?;
 
     let update = RecordingStateUpdate {
-        state: state.recording_state.clone(),
-        session_id: state.active_session_id.clone(),
+        state: recording_state,
+        session_id: session.active_session_id.clone(),
     };
 
     let _ = app.emit("recording-state-changed", &update);
@@ -86,205 +131,190 @@ pub fn signal_stop(
 /// Get current recording state.
 #[tauri::command]
 pub fn get_recording_state(state: AppStateGuard<'_>) -> Result<RecordingStateUpdate, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let session = state.session()?;
     Ok(RecordingStateUpdate {
-        state: state.recording_state.clone(),
-        session_id: state.active_session_id.clone(),
+        state: session.recording_state,
+        session_id: session.active_session_id.clone(),
     })
 }
 
-/// Receive an audio chunk from the renderer for VAD inspection.
-/// Actual transcription is handled in `finalize_session`.
-/// Returns current accumulated transcription (empty during streaming).
+/// Receive a PCM chunk (at `RecordingSettings.sample_rate`) from the renderer.
+/// Chunks are decoded in the background and partial results are pushed as
+/// `transcription-partial` events. Chunks sent while paused are dropped.
+/// Returns the stable + tentative text accumulated so far.
 #[tauri::command]
 pub fn process_audio_chunk(
-    _state: AppStateGuard<'_>,
-    _options: ProcessChunkOptions,
+    state: AppStateGuard<'_>,
+    options: ProcessChunkOptions,
 ) -> Result<String, String> {
-    // In Tauri, the renderer-side MediaRecorder sends audio chunks here.
-    // Streaming VAD and partial transcription would run here against whisper-rs.
-    // For this skeleton, we acknowledge receipt and return empty string.
-    Ok(String::new())
+    let mut session = state.session()?;
+    let paused = session.recording_state == RecordingState::Paused;
+    let streaming = session
+        .streaming
+        .as_mut()
+        .filter(|s| s.session_id() == options.session_id)
+        .ok_or_else(|| format!("Unknown session: {}", options.session_id))?;
+    if !paused {
+        streaming.push_chunk(&options.audio_chunk);
+    }
+    Ok(streaming.current_text())
 }
 
-/// Finalize the recording session: run full transcription, optionally format,
-/// save to DB, transition state back to Idle.
+/// Finalize the stopped recording session: run full transcription, optionally
+/// format, and save to DB.
+/// Transitions: Processing -> Idle, or Processing -> Error on failure.
 #[tauri::command]
 pub async fn finalize_session(
     state: AppStateGuard<'_>,
     app: tauri::AppHandle,
     options: FinalizeSessionOptions,
 ) -> Result<String, String> {
-    // Retrieve settings needed for transcription
-    let (language, formatter_config, ollama_url) = {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        let language = state
-            .settings
-            .dictation
-            .as_ref()
-            .and_then(|d| {
-                if d.auto_detect_enabled {
-                    None
-                } else {
-                    Some(d.selected_language.clone())
-                }
-            });
-        let formatter_config = state.settings.formatter_config.clone();
-        let ollama_url = state
-            .settings
-            .model_providers_config
-            .as_ref()
-            .and_then(|c| c.ollama.as_ref())
-            .map(|o| o.url.clone());
-        (language, formatter_config, ollama_url)
+    let (streaming, cancel, timeline) = {
+        let mut session = state.session()?;
+        // A session cancelled before it got here is no longer active, and one
+        // already being finalized is rejected.
+        session.claim_finalize(&options.session_id)?;
+        (
+            session.streaming.take(),
+            session.cancel.clone().unwrap_or_default(),
+            session.timeline.clone(),
+        )
     };
-
-    // NOTE: Actual whisper-rs transcription would happen here.
-    // The audio_file_path provides the WAV file recorded by the renderer.
-    // For this skeleton, we return a placeholder transcription.
-    let raw_text = if options.audio_file_path.is_some() {
-        // Real implementation: load WAV file, run whisper-rs inference
-        transcribe_audio_file(options.audio_file_path.as_deref(), language.as_deref()).await
-    } else {
-        Ok(String::new())
-    }?;
-
-    // Optional Ollama formatting
-    let final_text = if !raw_text.is_empty() {
-        if let Some(ref fc) = formatter_config {
-            if fc.enabled {
-                if let Some(ref url) = ollama_url {
-                    if let Some(ref model_id) = fc.model_id {
-                        format_with_ollama(url, model_id, &raw_text)
-                            .await
-                            .unwrap_or(raw_text.clone())
-                    } else {
-                        raw_text.clone()
-                    }
-                } else {
-                    raw_text.clone()
-                }
-            } else {
-                raw_text.clone()
+    // Without a recorded file, re-run the full pipeline over the streamed
+    // audio; partials were decoded window by window and lack context.
+    let streamed_audio = streaming
+        .filter(|s| s.session_id() == options.session_id)
+        .map(|mut s| s.take_audio())
+        .filter(|samples| !samples.is_empty());
+    let has_audio = options.audio_file_path.is_some() || streamed_audio.is_some();
+    let on_progress = |stage, percent| {
+        let progress = TranscriptionProgress {
+            session_id: options.session_id.clone(),
+            stage,
+            percent,
+        };
+        let _ = app.emit("transcription-progress", &progress);
+    };
+    let output = async {
+        let config = PipelineConfig::from_state(&state)?;
+        match (options.audio_file_path.as_deref(), streamed_audio) {
+            (Some(audio_path), _) => {
+                pipeline::process_file(&config, audio_path, &cancel, on_progress).await
+            }
+            (None, Some(samples)) => {
+                let rate = WHISPER_SAMPLE_RATE;
+                pipeline::process_samples(&config, samples, rate, &cancel, on_progress).await
             }
-        } else {
-            raw_text.clone()
+            (None, None) => Ok(PipelineOutput::default()),
         }
-    } else {
-        raw_text.clone()
-    };
-
-    // Save to database
-    {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        let meta = serde_json::json!({
-            "sessionId": options.session_id,
-            "source": "microphone"
-        });
-        state
-            .db
-            .create_transcription(
-                &final_text,
-                language.as_deref().or(Some("ja")),
-                options.audio_file_path.as_deref(),
-                None,
-                Some("whisper-local"),
-                None,
-                Some(&meta),
-            )
-            .map_err(|e| e.to_string())?;
     }
+    .await;
+    let output = match output {
+        Ok(output) => output,
+        // `cancel_session` has already ended the session.
+        Err(e) if cancel.is_cancelled() => {
+            log::info!("Session {} cancelled during processing", options.session_id);
+            return Err(e);
+        }
+        Err(e) => {
+            log::error!("Transcription failed: {e}");
+            let recording_state = {
+                let mut session = state.session()?;
+                session.check_session(&options.session_id)?;
+                session.apply(SessionEvent::Fail)?
+            };
+            let update = RecordingStateUpdate {
+                state: recording_state,
+                session_id: None,
+            };
+            let _ = app.emit("recording-state-changed", &update);
+            return Err(e);
+        }
+    };
 
-    // Transition back to Idle
-    {
-        let mut state = state.lock().map_err(|e| e.to_string())?;
-        state.recording_state = RecordingState::Idle;
-        state.active_session_id = None;
-    }
+    // Save and end the session under the session lock, so a concurrent
+    // `cancel_session` either lands first and prevents the save or finds the
+    // session already finished.
+    let (recording_state, saved) = {
+        let mut session = state.session()?;
+        cancel.check()?;
+        session.check_session(&options.session_id)?;
+        session.check_transition(SessionEvent::Complete)?;
+        let saved = save_session(&state, &options, &output, timeline.as_ref(), has_audio);
+        let event = match saved {
+            Ok(()) => SessionEvent::Complete,
+            Err(_) => SessionEvent::Fail,
+        };
+        (session.apply(event)?, saved)
+    };
 
     let update = RecordingStateUpdate {
-        state: RecordingState::Idle,
+        state: recording_state,
         session_id: None,
     };
     let _ = app.emit("recording-state-changed", &update);
-    let _ = app.emit("transcription-completed", &final_text);
+    saved?;
+    let _ = app.emit("transcription-completed", &output.text);
+
+    Ok(output.text)
+}
 
-    Ok(final_text)
+fn save_session(
+    state: &AppState,
+    options: &FinalizeSessionOptions,
+    output: &PipelineOutput,
+    timeline: Option<&SessionTimeline>,
+    has_audio: bool,
+) -> Result<(), String> {
+    let db = state.db()?;
+    let meta = serde_json::json!({
+        "sessionId": options.session_id,
+        "source": "microphone",
+        "pausedIntervals": timeline.map(|t| t.paused_intervals()),
+        "speakingDurationSecs": timeline.map(|t| t.speaking_duration().as_secs_f64()),
+        "languageProbability": output.language_probability,
+        "speechSegments": output.speech_segments,
+        "filteredSegments": output.filtered_segments,
+        "lowConfidenceSpans": output.low_confidence_spans,
+        "firedRules": output.fired_rules,
+    });
+    // Segments are saved with the transcription or not at all.
+    db.transaction(|| {
+        let id = db.create_transcription(
+            &output.text,
+            output.language.as_deref(),
+            options.audio_file_path.as_deref(),
+            output.confidence,
+            has_audio.then_some(output.duration_secs),
+            Some("whisper-local"),
+            output.formatting_model.as_deref(),
+            Some(&meta),
+        )?;
+        db.save_transcription_segments(id, &output.segments)
+    })
+    .map_err(|e| e.to_string())
 }
 
-/// Cancel the active recording session without processing.
+/// Cancel the active recording session, aborting any processing already
+/// under way; a cancelled session is never saved.
+/// Transitions: Recording | Paused | Processing -> Cancelled.
 #[tauri::command]
 pub fn cancel_session(
     state: AppStateGuard<'_>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.recording_state = RecordingState::Idle;
-    state.active_session_id = None;
+    let mut session = state.session()?;
+    let cancel = session.cancel.clone();
+    // Firing the token (and ending the session, which drops the streaming
+    // session) stops the streaming worker and aborts any decode under way.
+    let recording_state = session.apply(SessionEvent::Cancel)?;
+    if let Some(cancel) = cancel {
+        cancel.cancel();
+    }
     let update = RecordingStateUpdate {
-        state: RecordingState::Idle,
+        state: recording_state,
         session_id: None,
     };
     let _ = app.emit("recording-state-changed", &update);
     Ok(())
 }
-
-/// Perform Whisper transcription on a WAV file.
-/// In a full implementation this calls whisper-rs; here we return a stub.
-async fn transcribe_audio_file(
-    audio_path: Option<&str>,
-    _language: Option<&str>,
-) -> Result<String, String> {
-    // TODO: Integrate whisper-rs once the whisper.cpp shared library is available.
-    // Example integration (pseudo-code):
-    //
-    //   let model_path = get_selected_model_path()?;
-    //   let ctx = WhisperContext::new(&model_path)?;
-    //   let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-    //   if let Some(lang) = language { params.set_language(lang); }
-    //   let pcm = read_wav_as_f32(audio_path)?;
-    //   let state = ctx.create_state()?;
-    //   state.full(params, &pcm)?;
-    //   let segments: Vec<String> = (0..state.full_n_segments())
-    //       .map(|i| state.full_get_segment_text(i).unwrap_or_default())
-    //       .collect();
-    //   Ok(segments.join(""))
-    log::warn!(
-        "transcribe_audio_file: whisper-rs integration not yet implemented. \
-         Audio path: {:?}. Returning empty transcription.",
-        audio_path
-    );
-    Ok(String::new())
-}
-
-/// Call Ollama to format/clean up the raw transcription text.
-async fn format_with_ollama(
-    ollama_url: &str,
-    model_id: &str,
-    text: &str,
-) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let prompt = format!(
-        "以下の日本語音声認識テキストを自然な文章に整形してください。\
-         句読点を適切に追加し、不要な言い淀みを除去してください。\
-         テキストのみを返し、説明は不要です。\n\n{text}"
-    );
-    let body = serde_json::json!({
-        "model": model_id,
-        "prompt": prompt,
-        "stream": false
-    });
-    let response = client
-        .post(format!("{}/api/generate", ollama_url.trim_end_matches('/')))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-    Ok(json
-        .get("response")
-        .and_then(|v| v.as_str())
-        .unwrap_or(text)
-        .to_string())
-}
@@ -1,13 +1,11 @@
 use crate::state::{AppSettingsData, AppState};
-use std::sync::Mutex;
 use tauri::State;
 
-type AppStateGuard<'a> = State<'a, Mutex<AppState>>;
+type AppStateGuard<'a> = State<'a, AppState>;
 
 #[tauri::command]
 pub fn get_settings(state: AppStateGuard) -> Result<AppSettingsData, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.settings.clone())
+    Ok(state.settings()?.clone())
 }
 
 #[tauri::command]
@@ -15,12 +13,14 @@ pub fn update_settings(
     state: AppStateGuard,
     settings: AppSettingsData,
 ) -> Result<AppSettingsData, String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings = settings.clone();
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())?;
+    state.models.set_idle_timeout(
+        settings
+            .transcription
+            .as_ref()
+            .and_then(|t| t.model_idle_timeout_secs),
+    );
+    state.update_settings(|s| *s = settings.clone())?;
+    state.preload_speech_model()?;
     Ok(settings)
 }
 
@@ -28,9 +28,8 @@ pub fn update_settings(
 pub fn get_ui_settings(
     state: AppStateGuard,
 ) -> Result<crate::state::UiSettings, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
     Ok(state
-        .settings
+        .settings()?
         .ui
         .clone()
         .unwrap_or(crate::state::UiSettings {
@@ -44,20 +43,14 @@ pub fn set_ui_settings(
     state: AppStateGuard,
     ui: crate::state::UiSettings,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings.ui = Some(ui);
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+    state.update_settings(|s| s.ui = Some(ui))
 }
 
 #[tauri::command]
 pub fn get_transcription_settings(
     state: AppStateGuard,
 ) -> Result<Option<crate::state::TranscriptionSettings>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.settings.transcription.clone())
+    Ok(state.settings()?.transcription.clone())
 }
 
 #[tauri::command]
@@ -65,20 +58,18 @@ pub fn set_transcription_settings(
     state: AppStateGuard,
     transcription: crate::state::TranscriptionSettings,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings.transcription = Some(transcription);
     state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+        .models
+        .set_idle_timeout(transcription.model_idle_timeout_secs);
+    state.update_settings(|s| s.transcription = Some(transcription))?;
+    state.preload_speech_model()
 }
 
 #[tauri::command]
 pub fn get_shortcut_settings(
     state: AppStateGuard,
 ) -> Result<Option<crate::state::ShortcutsSettings>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.settings.shortcuts.clone())
+    Ok(state.settings()?.shortcuts.clone())
 }
 
 #[tauri::command]
@@ -86,20 +77,14 @@ pub fn set_shortcut_settings(
     state: AppStateGuard,
     shortcuts: crate::state::ShortcutsSettings,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings.shortcuts = Some(shortcuts);
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+    state.update_settings(|s| s.shortcuts = Some(shortcuts))
 }
 
 #[tauri::command]
 pub fn get_formatter_config(
     state: AppStateGuard,
 ) -> Result<Option<crate::state::FormatterConfig>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.settings.formatter_config.clone())
+    Ok(state.settings()?.formatter_config.clone())
 }
 
 #[tauri::command]
@@ -107,20 +92,14 @@ pub fn set_formatter_config(
     state: AppStateGuard,
     config: crate::state::FormatterConfig,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings.formatter_config = Some(config);
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+    state.update_settings(|s| s.formatter_config = Some(config))
 }
 
 #[tauri::command]
 pub fn get_dictation_settings(
     state: AppStateGuard,
 ) -> Result<Option<crate::state::DictationSettings>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.settings.dictation.clone())
+    Ok(state.settings()?.dictation.clone())
 }
 
 #[tauri::command]
@@ -128,20 +107,14 @@ pub fn set_dictation_settings(
     state: AppStateGuard,
     dictation: crate::state::DictationSettings,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings.dictation = Some(dictation);
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+    state.update_settings(|s| s.dictation = Some(dictation))
 }
 
 #[tauri::command]
 pub fn get_preferences(
     state: AppStateGuard,
 ) -> Result<Option<crate::state::AppPreferences>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(state.settings.preferences.clone())
+    Ok(state.settings()?.preferences.clone())
 }
 
 #[tauri::command]
@@ -149,12 +122,7 @@ pub fn set_preferences(
     state: AppStateGuard,
     preferences: crate::state::AppPreferences,
 ) -> Result<(), String> {
-    let mut state = state.lock().map_err(|e| e.to_string())?;
-    state.settings.preferences = Some(preferences);
-    state
-        .db
-        .save_settings(&state.settings)
-        .map_err(|e| e.to_string())
+    state.update_settings(|s| s.preferences = Some(preferences))
 }
 
 #[tauri::command]
@@ -163,15 +131,12 @@ pub fn sync_auto_launch(
     app: tauri::AppHandle,
 ) -> Result<(), String> {
     use tauri_plugin_autostart::ManagerExt;
-    let launch_at_login = {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        state
-            .settings
-            .preferences
-            .as_ref()
-            .and_then(|p| p.launch_at_login)
-            .unwrap_or(false)
-    };
+    let launch_at_login = state
+        .settings()?
+        .preferences
+        .as_ref()
+        .and_then(|p| p.launch_at_login)
+        .unwrap_or(false);
 
     let autostart = app.autolaunch();
     if launch_at_login {
@@ -181,3 +146,33 @@ pub fn sync_auto_launch(
     }
     Ok(())
 }
+
+#[tauri::command]
+pub fn get_text_normalization_settings(
+    state: AppStateGuard,
+) -> Result<Option<crate::state::TextNormalizationSettings>, String> {
+    Ok(state.settings()?.text_normalization.clone())
+}
+
+#[tauri::command]
+pub fn set_text_normalization_settings(
+    state: AppStateGuard,
+    text_normalization: crate::state::TextNormalizationSettings,
+) -> Result<(), String> {
+    state.update_settings(|s| s.text_normalization = Some(text_normalization))
+}
+
+#[tauri::command]
+pub fn get_itn_settings(
+    state: AppStateGuard,
+) -> Result<Option<crate::state::ItnSettings>, String> {
+    Ok(state.settings()?.inverse_text_normalization.clone())
+}
+
+#[tauri::command]
+pub fn set_itn_settings(
+    state: AppStateGuard,
+    itn: crate::state::ItnSettings,
+) -> Result<(), String> {
+    state.update_settings(|s| s.inverse_text_normalization = Some(itn))
+}
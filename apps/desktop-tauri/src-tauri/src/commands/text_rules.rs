@@ -0,0 +1,41 @@
+use crate::db::{TextRule, TextRuleInput};
+use crate::pipeline::rules;
+use crate::state::AppState;
+use tauri::State;
+
+type AppStateGuard<'a> = State<'a, AppState>;
+
+/// All rules in the order they are applied.
+#[tauri::command]
+pub fn get_text_rules(state: AppStateGuard) -> Result<Vec<TextRule>, String> {
+    let db = state.db()?;
+    db.get_text_rules().map_err(|e| e.to_string())
+}
+
+/// Add a rule after the existing ones. Invalid patterns are rejected.
+#[tauri::command]
+pub fn add_text_rule(state: AppStateGuard, input: TextRuleInput) -> Result<i64, String> {
+    rules::compile(input.kind, &input.pattern)?;
+    let db = state.db()?;
+    db.create_text_rule(&input).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_text_rule(state: AppStateGuard, id: i64, input: TextRuleInput) -> Result<(), String> {
+    rules::compile(input.kind, &input.pattern)?;
+    let db = state.db()?;
+    db.update_text_rule(id, &input).map_err(|e| e.to_string())
+}
+
+/// Set the application order to `ids`.
+#[tauri::command]
+pub fn reorder_text_rules(state: AppStateGuard, ids: Vec<i64>) -> Result<(), String> {
+    let db = state.db()?;
+    db.reorder_text_rules(&ids).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_text_rule(state: AppStateGuard, id: i64) -> Result<(), String> {
+    let db = state.db()?;
+    db.delete_text_rule(id).map_err(|e| e.to_string())
+}
@@ -1,10 +1,9 @@
-use crate::db::Transcription;
+use crate::db::{Transcription, TranscriptionSegment};
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
 use tauri::State;
 
-type AppStateGuard<'a> = State<'a, Mutex<AppState>>;
+type AppStateGuard<'a> = State<'a, AppState>;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +18,7 @@ pub struct CreateTranscriptionInput {
     pub text: String,
     pub language: Option<String>,
     pub audio_file: Option<String>,
+    pub confidence: Option<f64>,
     pub duration: Option<i64>,
     pub speech_model: Option<String>,
     pub formatting_model: Option<String>,
@@ -30,12 +30,10 @@ pub fn get_transcriptions(
     state: AppStateGuard,
     options: Option<GetTranscriptionsOptions>,
 ) -> Result<Vec<Transcription>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
+    let db = state.db()?;
     let limit = options.as_ref().and_then(|o| o.limit).unwrap_or(50);
     let offset = options.as_ref().and_then(|o| o.offset).unwrap_or(0);
-    state
-        .db
-        .get_transcriptions(limit, offset)
+    db.get_transcriptions(limit, offset)
         .map_err(|e| e.to_string())
 }
 
@@ -44,8 +42,8 @@ pub fn get_transcription(
     state: AppStateGuard,
     id: i64,
 ) -> Result<Option<Transcription>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.db.get_transcription(id).map_err(|e| e.to_string())
+    let db = state.db()?;
+    db.get_transcription(id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -53,35 +51,39 @@ pub fn save_transcription(
     state: AppStateGuard,
     input: CreateTranscriptionInput,
 ) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state
-        .db
-        .create_transcription(
-            &input.text,
-            input.language.as_deref(),
-            input.audio_file.as_deref(),
-            input.duration,
-            input.speech_model.as_deref(),
-            input.formatting_model.as_deref(),
-            input.meta.as_ref(),
-        )
+    let db = state.db()?;
+    db.create_transcription(
+        &input.text,
+        input.language.as_deref(),
+        input.audio_file.as_deref(),
+        input.confidence,
+        input.duration,
+        input.speech_model.as_deref(),
+        input.formatting_model.as_deref(),
+        input.meta.as_ref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Timed segments of a transcription, in order.
+#[tauri::command]
+pub fn get_transcription_segments(
+    state: AppStateGuard,
+    transcription_id: i64,
+) -> Result<Vec<TranscriptionSegment>, String> {
+    let db = state.db()?;
+    db.get_transcription_segments(transcription_id)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn delete_transcription(state: AppStateGuard, id: i64) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state
-        .db
-        .delete_transcription(id)
-        .map_err(|e| e.to_string())
+    let db = state.db()?;
+    db.delete_transcription(id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn delete_all_transcriptions(state: AppStateGuard) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state
-        .db
-        .delete_all_transcriptions()
-        .map_err(|e| e.to_string())
+    let db = state.db()?;
+    db.delete_all_transcriptions().map_err(|e| e.to_string())
 }
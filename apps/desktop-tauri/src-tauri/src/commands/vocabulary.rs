@@ -0,0 +1,193 @@
+use crate::db::{NewVocabularyTerm, VocabularyTerm};
+use crate::state::AppState;
+use tauri::State;
+
+type AppStateGuard<'a> = State<'a, AppState>;
+
+#[tauri::command]
+pub fn get_vocabulary(state: AppStateGuard) -> Result<Vec<VocabularyTerm>, String> {
+    let db = state.db()?;
+    db.get_vocabulary().map_err(|e| e.to_string())
+}
+
+/// Add a term; adding an existing term updates its reading and replacement.
+#[tauri::command]
+pub fn add_vocabulary_term(state: AppStateGuard, input: NewVocabularyTerm) -> Result<i64, String> {
+    let input = normalize(input).ok_or_else(|| "Term must not be empty".to_string())?;
+    let db = state.db()?;
+    db.upsert_vocabulary_term(&input).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_vocabulary_term(state: AppStateGuard, id: i64) -> Result<(), String> {
+    let db = state.db()?;
+    db.delete_vocabulary_term(id).map_err(|e| e.to_string())
+}
+
+/// Import terms from a CSV or TSV file with one `term,reading,replacement`
+/// entry per line; reading and replacement may be left empty. Fields may be
+/// quoted as in RFC 4180, and lines starting with `#` are skipped. Returns
+/// the number of terms imported.
+#[tauri::command]
+pub fn import_vocabulary(state: AppStateGuard, path: String) -> Result<usize, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+    let terms = parse_terms(&content);
+    let db = state.db()?;
+    db.import_vocabulary(&terms).map_err(|e| e.to_string())
+}
+
+fn parse_terms(content: &str) -> Vec<NewVocabularyTerm> {
+    parse_records(content)
+        .into_iter()
+        .filter_map(|fields| {
+            let mut fields = fields.into_iter();
+            normalize(NewVocabularyTerm {
+                term: fields.next()?,
+                reading: fields.next(),
+                replacement: fields.next(),
+            })
+        })
+        .collect()
+}
+
+/// Split CSV, or TSV when the first entry contains a tab, into records.
+/// A field starting with `"` runs to the next lone `"`, so it may hold
+/// delimiters and line breaks; `""` inside it is a literal quote.
+fn parse_records(content: &str) -> Vec<Vec<String>> {
+    let content = content.trim_start_matches('\u{feff}');
+    let is_comment = |line: &str| line.trim_start().starts_with('#');
+    let delimiter = match content.lines().find(|line| !is_comment(line)) {
+        Some(line) if line.contains('\t') => '\t',
+        _ => ',',
+    };
+
+    let mut records = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        if is_comment(rest) {
+            rest = rest.split_once('\n').map_or("", |(_, next)| next);
+            continue;
+        }
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut next = "";
+        let mut chars = rest.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' if in_quotes => {
+                    if chars.next_if(|&(_, c)| c == '"').is_some() {
+                        field.push('"');
+                    } else {
+                        in_quotes = false;
+                    }
+                }
+                '"' if field.trim().is_empty() => {
+                    field.clear();
+                    in_quotes = true;
+                }
+                _ if in_quotes => field.push(c),
+                '\n' => {
+                    next = &rest[i + 1..];
+                    break;
+                }
+                '\r' => {}
+                _ if c == delimiter => record.push(std::mem::take(&mut field)),
+                _ => field.push(c),
+            }
+        }
+        record.push(field);
+        records.push(record);
+        rest = next;
+    }
+    records
+}
+
+/// Trim every field, treat empty optional fields as unset, and reject an empty
+/// term.
+fn normalize(input: NewVocabularyTerm) -> Option<NewVocabularyTerm> {
+    let optional = |field: Option<String>| {
+        field
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+    };
+    let term = input.term.trim().to_string();
+    (!term.is_empty()).then(|| NewVocabularyTerm {
+        term,
+        reading: optional(input.reading),
+        replacement: optional(input.replacement),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_records() {
+        let cases: &[(&str, &str, &[&[&str]])] = &[
+            ("empty", "", &[]),
+            ("plain", "a,b,c\nd,,f", &[&["a", "b", "c"], &["d", "", "f"]]),
+            ("crlf", "a,b\r\nc\r\n", &[&["a", "b"], &["c"]]),
+            ("tsv", "a\tb,c\nd\te", &[&["a", "b,c"], &["d", "e"]]),
+            ("bom", "\u{feff}a,b", &[&["a", "b"]]),
+            (
+                "comments",
+                "# term,reading\na\n  # note\nb",
+                &[&["a"], &["b"]],
+            ),
+            ("tsv after a comment", "# a,b\na\tb", &[&["a", "b"]]),
+            ("quoted delimiter", "\"a, b\",c", &[&["a, b", "c"]]),
+            (
+                "escaped quote",
+                "\"say \"\"hi\"\"\",x",
+                &[&["say \"hi\"", "x"]],
+            ),
+            (
+                "quoted line break",
+                "\"a\nb\",c\nd",
+                &[&["a\nb", "c"], &["d"]],
+            ),
+            ("quoted hash", "\"#tag\",x", &[&["#tag", "x"]]),
+            ("quote inside a field", "5\" disk,x", &[&["5\" disk", "x"]]),
+            ("space before a quote", " \"a,b\" ,c", &[&["a,b ", "c"]]),
+        ];
+        for &(name, content, expected) in cases {
+            let expected: Vec<Vec<String>> = expected
+                .iter()
+                .map(|r| r.iter().map(|f| f.to_string()).collect())
+                .collect();
+            assert_eq!(parse_records(content), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn parses_terms() {
+        let terms = parse_terms(
+            "# term,reading,replacement\n\
+             Kotoba,ことば,\n\
+             gh,,GitHub\n\
+             \"Smith, J.\",スミス\n\
+             ,orphan reading\n\
+             \n",
+        );
+        let terms: Vec<_> = terms
+            .iter()
+            .map(|t| {
+                (
+                    t.term.as_str(),
+                    t.reading.as_deref(),
+                    t.replacement.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            terms,
+            [
+                ("Kotoba", Some("ことば"), None),
+                ("gh", None, Some("GitHub")),
+                ("Smith, J.", Some("スミス"), None),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::state::AppSettingsData;
+use crate::whisper::Segment;
 use rusqlite::{Connection, Result as SqlResult};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -21,9 +22,134 @@ pub struct Transcription {
     pub updated_at: i64,
 }
 
-/// Model record matching the DB schema.
+/// Timed whisper segment belonging to a transcription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionSegment {
+    pub id: i64,
+    pub transcription_id: i64,
+    /// Position within the transcription, from 0.
+    pub segment_index: i64,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub no_speech_prob: Option<f64>,
+    pub avg_logprob: Option<f64>,
+    pub created_at: i64,
+}
+
+/// Custom vocabulary entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VocabularyTerm {
+    pub id: i64,
+    /// Word as it should be recognised, e.g. a product or colleague's name.
+    pub term: String,
+    /// Pronunciation in kana. Whisper often writes unfamiliar names phonetically,
+    /// so occurrences of the reading are corrected as well.
+    pub reading: Option<String>,
+    /// Text substituted for the term in transcripts; the term itself if unset.
+    pub replacement: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Vocabulary entry to add or import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewVocabularyTerm {
+    pub term: String,
+    pub reading: Option<String>,
+    pub replacement: Option<String>,
+}
+
+/// How a text rule matches.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    /// Plain find and replace.
+    Literal,
+    /// Regular expression; the replacement may use `$1` or `${name}`.
+    Regex,
+    /// Spoken trigger phrase expanded into a saved snippet.
+    Snippet,
+}
+
+impl RuleKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Literal => "literal",
+            Self::Regex => "regex",
+            Self::Snippet => "snippet",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "literal" => Some(Self::Literal),
+            "regex" => Some(Self::Regex),
+            "snippet" => Some(Self::Snippet),
+            _ => None,
+        }
+    }
+}
+
+/// Post-processing rule, applied in `position` order after formatting.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct TextRule {
+    pub id: i64,
+    pub kind: RuleKind,
+    /// Text to find, regular expression, or trigger phrase.
+    pub pattern: String,
+    /// Replacement text or snippet body.
+    pub replacement: String,
+    pub position: i64,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Text rule to add or update.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextRuleInput {
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub replacement: String,
+    pub enabled: Option<bool>,
+}
+
+/// Outcome of the last integrity check of a model file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelStatus {
+    /// The file matched `checksum` when last checked.
+    Verified,
+    /// The file did not match; it has to be downloaded again.
+    Corrupt,
+}
+
+impl ModelStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Verified => "verified",
+            Self::Corrupt => "corrupt",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "verified" => Some(Self::Verified),
+            "corrupt" => Some(Self::Corrupt),
+            _ => None,
+        }
+    }
+}
+
+/// Model record matching the DB schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Model {
     pub id: String,
     pub provider: String,
@@ -38,10 +164,27 @@ pub struct Model {
     pub downloaded_at: Option<i64>,
     pub speed: Option<f64>,
     pub accuracy: Option<f64>,
+    /// `None` until the file has been checked.
+    pub status: Option<ModelStatus>,
+    /// Unix time of the last integrity check.
+    pub verified_at: Option<i64>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+impl Model {
+    /// A speech model with only its identity filled in.
+    pub fn speech(id: &str, provider: &str, name: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            provider: provider.to_string(),
+            name: name.to_string(),
+            model_type: "speech".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
 /// Thin wrapper around a SQLite connection.
 pub struct Database {
     conn: Connection,
@@ -54,7 +197,12 @@ impl Database {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).ok();
         }
-        let conn = Connection::open(&path)?;
+        Self::with_connection(Connection::open(&path)?)
+    }
+
+    /// Wrap `conn`, enabling foreign keys and migrating it like the app
+    /// database.
+    pub fn with_connection(conn: Connection) -> SqlResult<Self> {
         let db = Self { conn };
         db.run_migrations()?;
         Ok(db)
@@ -70,6 +218,7 @@ impl Database {
     fn run_migrations(&self) -> SqlResult<()> {
         self.conn.execute_batch(
             "PRAGMA journal_mode=WAL;
+            PRAGMA foreign_keys=ON;
 
             CREATE TABLE IF NOT EXISTS transcriptions (
                 id          INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -86,6 +235,42 @@ impl Database {
                 updated_at  INTEGER NOT NULL DEFAULT (unixepoch())
             );
 
+            CREATE TABLE IF NOT EXISTS transcription_segments (
+                id               INTEGER PRIMARY KEY AUTOINCREMENT,
+                transcription_id INTEGER NOT NULL
+                                 REFERENCES transcriptions (id) ON DELETE CASCADE,
+                segment_index    INTEGER NOT NULL,
+                start_ms         INTEGER NOT NULL,
+                end_ms           INTEGER NOT NULL,
+                text             TEXT    NOT NULL,
+                no_speech_prob   REAL,
+                avg_logprob      REAL,
+                created_at       INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+
+            CREATE INDEX IF NOT EXISTS transcription_segments_transcription_idx
+                ON transcription_segments (transcription_id, segment_index);
+
+            CREATE TABLE IF NOT EXISTS vocabulary (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                term        TEXT    NOT NULL UNIQUE,
+                reading     TEXT,
+                replacement TEXT,
+                created_at  INTEGER NOT NULL DEFAULT (unixepoch()),
+                updated_at  INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+
+            CREATE TABLE IF NOT EXISTS text_rules (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind        TEXT    NOT NULL,
+                pattern     TEXT    NOT NULL,
+                replacement TEXT    NOT NULL,
+                position    INTEGER NOT NULL,
+                enabled     INTEGER NOT NULL DEFAULT 1,
+                created_at  INTEGER NOT NULL DEFAULT (unixepoch()),
+                updated_at  INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+
             CREATE TABLE IF NOT EXISTS app_settings (
                 id          INTEGER PRIMARY KEY,
                 data        TEXT    NOT NULL,
@@ -109,6 +294,8 @@ impl Database {
                 original_model TEXT,
                 speed       REAL,
                 accuracy    REAL,
+                status      TEXT,
+                verified_at INTEGER,
                 created_at  INTEGER NOT NULL DEFAULT (unixepoch()),
                 updated_at  INTEGER NOT NULL DEFAULT (unixepoch()),
                 PRIMARY KEY (provider, id)
@@ -117,7 +304,36 @@ impl Database {
             CREATE INDEX IF NOT EXISTS models_provider_idx ON models (provider);
             CREATE INDEX IF NOT EXISTS models_type_idx     ON models (type);
             ",
-        )
+        )?;
+
+        // Columns added after the table was first created
+        self.add_column("models", "status", "TEXT")?;
+        self.add_column("models", "verified_at", "INTEGER")
+    }
+
+    /// Add `column` to `table` unless it is already there.
+    fn add_column(&self, table: &str, column: &str, definition: &str) -> SqlResult<()> {
+        let exists = self
+            .conn
+            .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")?
+            .exists([table, column])?;
+        if !exists {
+            self.conn
+                .execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"))?;
+        }
+        Ok(())
+    }
+
+    /// Run `f` in a transaction, committed if it returns `Ok`. Called inside
+    /// another `transaction`, `f` joins the outer one.
+    pub fn transaction<T>(&self, f: impl FnOnce() -> SqlResult<T>) -> SqlResult<T> {
+        if !self.conn.is_autocommit() {
+            return f();
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let value = f()?;
+        tx.commit()?;
+        Ok(value)
     }
 
     // ── Settings ──────────────────────────────────────────────────────────────
@@ -221,11 +437,13 @@ impl Database {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_transcription(
         &self,
         text: &str,
         language: Option<&str>,
         audio_file: Option<&str>,
+        confidence: Option<f64>,
         duration: Option<i64>,
         speech_model: Option<&str>,
         formatting_model: Option<&str>,
@@ -233,13 +451,14 @@ impl Database {
     ) -> SqlResult<i64> {
         let meta_json = meta.map(|m| m.to_string());
         self.conn.execute(
-            "INSERT INTO transcriptions (text, language, audio_file, duration, speech_model,
-             formatting_model, meta)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO transcriptions (text, language, audio_file, confidence, duration,
+             speech_model, formatting_model, meta)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             rusqlite::params![
                 text,
                 language,
                 audio_file,
+                confidence,
                 duration,
                 speech_model,
                 formatting_model,
@@ -260,36 +479,276 @@ impl Database {
         Ok(())
     }
 
-    // ── Models ────────────────────────────────────────────────────────────────
+    // ── Transcription segments ────────────────────────────────────────────────
+    // Rows go away with their transcription through ON DELETE CASCADE.
 
-    pub fn get_models(&self) -> SqlResult<Vec<Model>> {
+    pub fn get_transcription_segments(
+        &self,
+        transcription_id: i64,
+    ) -> SqlResult<Vec<TranscriptionSegment>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, provider, name, type, size, context, description, local_path,
-                    size_bytes, checksum, downloaded_at, speed, accuracy, created_at, updated_at
-             FROM models ORDER BY created_at ASC",
+            "SELECT id, transcription_id, segment_index, start_ms, end_ms, text,
+                    no_speech_prob, avg_logprob, created_at
+             FROM transcription_segments
+             WHERE transcription_id = ?1
+             ORDER BY segment_index ASC",
+        )?;
+        let rows = stmt.query_map([transcription_id], |row| {
+            Ok(TranscriptionSegment {
+                id: row.get(0)?,
+                transcription_id: row.get(1)?,
+                segment_index: row.get(2)?,
+                start_ms: row.get(3)?,
+                end_ms: row.get(4)?,
+                text: row.get(5)?,
+                no_speech_prob: row.get(6)?,
+                avg_logprob: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Replace a transcription's segments with `segments`, in order, in one
+    /// transaction.
+    pub fn save_transcription_segments(
+        &self,
+        transcription_id: i64,
+        segments: &[Segment],
+    ) -> SqlResult<()> {
+        self.transaction(|| {
+            self.delete_transcription_segments(transcription_id)?;
+            let mut stmt = self.conn.prepare(
+                "INSERT INTO transcription_segments (transcription_id, segment_index,
+                 start_ms, end_ms, text, no_speech_prob, avg_logprob)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (index, segment) in segments.iter().enumerate() {
+                stmt.execute(rusqlite::params![
+                    transcription_id,
+                    index as i64,
+                    segment.start_ms,
+                    segment.end_ms,
+                    segment.text.trim(),
+                    segment.no_speech_prob,
+                    segment.avg_logprob,
+                ])?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn delete_transcription_segments(&self, transcription_id: i64) -> SqlResult<()> {
+        self.conn.execute(
+            "DELETE FROM transcription_segments WHERE transcription_id = ?1",
+            [transcription_id],
+        )?;
+        Ok(())
+    }
+
+    // ── Vocabulary ────────────────────────────────────────────────────────────
+
+    pub fn get_vocabulary(&self) -> SqlResult<Vec<VocabularyTerm>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, term, reading, replacement, created_at, updated_at
+             FROM vocabulary ORDER BY term ASC",
         )?;
         let rows = stmt.query_map([], |row| {
-            Ok(Model {
+            Ok(VocabularyTerm {
                 id: row.get(0)?,
-                provider: row.get(1)?,
-                name: row.get(2)?,
-                model_type: row.get(3)?,
-                size: row.get(4)?,
-                context: row.get(5)?,
-                description: row.get(6)?,
-                local_path: row.get(7)?,
-                size_bytes: row.get(8)?,
-                checksum: row.get(9)?,
-                downloaded_at: row.get(10)?,
-                speed: row.get(11)?,
-                accuracy: row.get(12)?,
-                created_at: row.get(13)?,
-                updated_at: row.get(14)?,
+                term: row.get(1)?,
+                reading: row.get(2)?,
+                replacement: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
             })
         })?;
         rows.collect()
     }
 
+    /// Add a term, or update the reading and replacement of an existing one.
+    pub fn upsert_vocabulary_term(&self, term: &NewVocabularyTerm) -> SqlResult<i64> {
+        self.conn.query_row(
+            "INSERT INTO vocabulary (term, reading, replacement)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(term) DO UPDATE SET
+               reading = excluded.reading,
+               replacement = excluded.replacement,
+               updated_at = unixepoch()
+             RETURNING id",
+            rusqlite::params![term.term, term.reading, term.replacement],
+            |row| row.get(0),
+        )
+    }
+
+    /// Upsert many terms in one transaction. Returns how many were written.
+    pub fn import_vocabulary(&self, terms: &[NewVocabularyTerm]) -> SqlResult<usize> {
+        self.transaction(|| {
+            for term in terms {
+                self.upsert_vocabulary_term(term)?;
+            }
+            Ok(terms.len())
+        })
+    }
+
+    pub fn delete_vocabulary_term(&self, id: i64) -> SqlResult<()> {
+        self.conn.execute("DELETE FROM vocabulary WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    // ── Text rules ────────────────────────────────────────────────────────────
+
+    pub fn get_text_rules(&self) -> SqlResult<Vec<TextRule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, kind, pattern, replacement, position, enabled, created_at, updated_at
+             FROM text_rules ORDER BY position ASC, id ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let kind: String = row.get(1)?;
+            Ok(TextRule {
+                id: row.get(0)?,
+                kind: RuleKind::parse(&kind).ok_or_else(|| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        1,
+                        rusqlite::types::Type::Text,
+                        format!("Unknown rule kind: {kind}").into(),
+                    )
+                })?,
+                pattern: row.get(2)?,
+                replacement: row.get(3)?,
+                position: row.get(4)?,
+                enabled: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Append a rule after the existing ones.
+    pub fn create_text_rule(&self, rule: &TextRuleInput) -> SqlResult<i64> {
+        self.conn.execute(
+            "INSERT INTO text_rules (kind, pattern, replacement, position, enabled)
+             VALUES (?1, ?2, ?3, (SELECT COALESCE(MAX(position), -1) + 1 FROM text_rules), ?4)",
+            rusqlite::params![
+                rule.kind.as_str(),
+                rule.pattern,
+                rule.replacement,
+                rule.enabled.unwrap_or(true),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update_text_rule(&self, id: i64, rule: &TextRuleInput) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE text_rules
+             SET kind = ?2, pattern = ?3, replacement = ?4,
+                 enabled = COALESCE(?5, enabled), updated_at = unixepoch()
+             WHERE id = ?1",
+            rusqlite::params![
+                id,
+                rule.kind.as_str(),
+                rule.pattern,
+                rule.replacement,
+                rule.enabled,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Renumber rules to follow `ids`; rules not listed keep their position.
+    pub fn reorder_text_rules(&self, ids: &[i64]) -> SqlResult<()> {
+        self.transaction(|| {
+            for (position, id) in ids.iter().enumerate() {
+                self.conn.execute(
+                    "UPDATE text_rules SET position = ?2, updated_at = unixepoch() WHERE id = ?1",
+                    rusqlite::params![id, position as i64],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn delete_text_rule(&self, id: i64) -> SqlResult<()> {
+        self.conn.execute("DELETE FROM text_rules WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    // ── Models ────────────────────────────────────────────────────────────────
+
+    pub fn get_models(&self) -> SqlResult<Vec<Model>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, provider, name, type, size, context, description, local_path,
+                    size_bytes, checksum, downloaded_at, speed, accuracy, status, verified_at,
+                    created_at, updated_at
+             FROM models ORDER BY created_at ASC",
+        )?;
+        let rows = stmt.query_map([], Self::model_from_row)?;
+        rows.collect()
+    }
+
+    pub fn get_model(&self, id: &str, provider: &str) -> SqlResult<Option<Model>> {
+        let result = self.conn.query_row(
+            "SELECT id, provider, name, type, size, context, description, local_path,
+                    size_bytes, checksum, downloaded_at, speed, accuracy, status, verified_at,
+                    created_at, updated_at
+             FROM models WHERE id = ?1 AND provider = ?2",
+            rusqlite::params![id, provider],
+            Self::model_from_row,
+        );
+        match result {
+            Ok(model) => Ok(Some(model)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn model_from_row(row: &rusqlite::Row) -> SqlResult<Model> {
+        Ok(Model {
+            id: row.get(0)?,
+            provider: row.get(1)?,
+            name: row.get(2)?,
+            model_type: row.get(3)?,
+            size: row.get(4)?,
+            context: row.get(5)?,
+            description: row.get(6)?,
+            local_path: row.get(7)?,
+            size_bytes: row.get(8)?,
+            checksum: row.get(9)?,
+            downloaded_at: row.get(10)?,
+            speed: row.get(11)?,
+            accuracy: row.get(12)?,
+            status: row
+                .get::<_, Option<String>>(13)?
+                .as_deref()
+                .and_then(ModelStatus::parse),
+            verified_at: row.get(14)?,
+            created_at: row.get(15)?,
+            updated_at: row.get(16)?,
+        })
+    }
+
+    /// Local file path of a downloaded model, looked up by id across providers.
+    pub fn get_model_local_path(&self, id: &str) -> SqlResult<Option<String>> {
+        let result = self.conn.query_row(
+            "SELECT local_path FROM models
+             WHERE id = ?1 AND local_path IS NOT NULL
+             ORDER BY downloaded_at DESC
+             LIMIT 1",
+            [id],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(path) => Ok(Some(path)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Upsert `model`. Its `status` and `verified_at` are ignored: only
+    /// [`Self::set_model_status`] records a check, and a new `local_path`
+    /// clears the previous one.
     pub fn save_model(&self, model: &Model) -> SqlResult<()> {
         self.conn.execute(
             "INSERT INTO models (id, provider, name, type, size, context, description,
@@ -301,6 +760,8 @@ impl Database {
                size_bytes = excluded.size_bytes,
                checksum = excluded.checksum,
                downloaded_at = excluded.downloaded_at,
+               status = CASE WHEN local_path IS excluded.local_path THEN status END,
+               verified_at = CASE WHEN local_path IS excluded.local_path THEN verified_at END,
                updated_at = unixepoch()",
             rusqlite::params![
                 model.id,
@@ -321,6 +782,94 @@ impl Database {
         Ok(())
     }
 
+    /// Record the outcome of checking the file at `local_path`. Does nothing
+    /// if the model has since been given another file.
+    pub fn set_model_status(
+        &self,
+        id: &str,
+        provider: &str,
+        local_path: &str,
+        status: ModelStatus,
+    ) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE models
+             SET status = ?4, verified_at = unixepoch(), updated_at = unixepoch()
+             WHERE id = ?1 AND provider = ?2 AND local_path = ?3",
+            rusqlite::params![id, provider, local_path, status.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Upsert `models` for `provider` and drop its rows that are no longer
+    /// listed, keeping the download record of files already on disk.
+    pub fn sync_catalog_models(&self, provider: &str, models: &[Model]) -> SqlResult<()> {
+        self.transaction(|| {
+            for model in models {
+                self.conn.execute(
+                    "INSERT INTO models (id, provider, name, type, size, context, description,
+                     checksum, speed, accuracy)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT(provider, id) DO UPDATE SET
+                       name = excluded.name,
+                       type = excluded.type,
+                       size = excluded.size,
+                       context = excluded.context,
+                       description = excluded.description,
+                       checksum = CASE WHEN models.local_path IS NULL
+                                       THEN excluded.checksum ELSE models.checksum END,
+                       speed = excluded.speed,
+                       accuracy = excluded.accuracy,
+                       updated_at = unixepoch()",
+                    rusqlite::params![
+                        model.id,
+                        provider,
+                        model.name,
+                        model.model_type,
+                        model.size,
+                        model.context,
+                        model.description,
+                        model.checksum,
+                        model.speed,
+                        model.accuracy,
+                    ],
+                )?;
+            }
+            let listed: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+            let retired: Vec<String> = {
+                let mut stmt = self.conn.prepare(
+                    "SELECT id FROM models WHERE provider = ?1 AND local_path IS NULL",
+                )?;
+                let ids = stmt.query_map([provider], |row| row.get::<_, String>(0))?;
+                ids.filter(|id| id.as_ref().map_or(true, |id| !listed.contains(&id.as_str())))
+                    .collect::<SqlResult<_>>()?
+            };
+            for id in retired {
+                self.conn.execute(
+                    "DELETE FROM models WHERE id = ?1 AND provider = ?2",
+                    rusqlite::params![id, provider],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Mark a model as not downloaded, restoring its expected `checksum`.
+    pub fn clear_model_download(
+        &self,
+        id: &str,
+        provider: &str,
+        checksum: Option<&str>,
+    ) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE models
+             SET local_path = NULL, size_bytes = NULL, downloaded_at = NULL,
+                 checksum = ?3, status = NULL, verified_at = NULL, updated_at = unixepoch()
+             WHERE id = ?1 AND provider = ?2",
+            rusqlite::params![id, provider, checksum],
+        )?;
+        Ok(())
+    }
+
     pub fn delete_model(&self, id: &str, provider: &str) -> SqlResult<()> {
         self.conn.execute(
             "DELETE FROM models WHERE id = ?1 AND provider = ?2",
@@ -329,3 +878,85 @@ impl Database {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> Database {
+        Database::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn segment(start_ms: i64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms: start_ms + 1000,
+            text: text.to_string(),
+            no_speech_prob: 0.1,
+            avg_logprob: -0.2,
+            tokens: Vec::new(),
+        }
+    }
+
+    fn transcription_with_segments(db: &Database, texts: &[&str]) -> i64 {
+        let id = db
+            .create_transcription(&texts.concat(), None, None, None, None, None, None, None)
+            .unwrap();
+        let segments: Vec<Segment> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| segment(i as i64 * 1000, text))
+            .collect();
+        db.save_transcription_segments(id, &segments).unwrap();
+        id
+    }
+
+    fn segment_texts(db: &Database, transcription_id: i64) -> Vec<String> {
+        db.get_transcription_segments(transcription_id)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.text)
+            .collect()
+    }
+
+    #[test]
+    fn saves_segments_in_order_and_replaces_them() {
+        let db = memory_db();
+        let id = transcription_with_segments(&db, &["今日は", "晴れです"]);
+
+        let segments = db.get_transcription_segments(id).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].segment_index, 1);
+        assert_eq!(segments[1].start_ms, 1000);
+        assert_eq!(segments[1].end_ms, 2000);
+        assert_eq!(segments[1].text, "晴れです");
+
+        db.save_transcription_segments(id, &[segment(0, "今日は晴れ")])
+            .unwrap();
+        assert_eq!(segment_texts(&db, id), ["今日は晴れ"]);
+
+        db.delete_transcription_segments(id).unwrap();
+        assert!(segment_texts(&db, id).is_empty());
+    }
+
+    #[test]
+    fn segments_are_deleted_with_their_transcription() {
+        let db = memory_db();
+        let first = transcription_with_segments(&db, &["a", "b"]);
+        let second = transcription_with_segments(&db, &["c"]);
+
+        db.delete_transcription(first).unwrap();
+        assert!(segment_texts(&db, first).is_empty());
+        assert_eq!(segment_texts(&db, second), ["c"]);
+
+        db.delete_all_transcriptions().unwrap();
+        assert!(segment_texts(&db, second).is_empty());
+    }
+
+    #[test]
+    fn segments_need_a_transcription() {
+        let db = memory_db();
+        let saved = db.save_transcription_segments(42, &[segment(0, "a")]);
+        assert!(saved.is_err());
+    }
+}
@@ -1,4 +1,3 @@
-use std::sync::Mutex;
 use crate::state::AppState;
 use tauri::{
     menu::{MenuBuilder, MenuItemBuilder},
@@ -6,9 +5,16 @@ use tauri::{
     Emitter, Manager, Runtime,
 };
 
+mod audio;
 mod commands;
 mod db;
+mod model_files;
+mod model_manager;
+mod pipeline;
+mod session;
 mod state;
+mod vad;
+mod whisper;
 
 /// Build the system tray menu and icon.
 fn build_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
@@ -52,11 +58,8 @@ fn build_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
 
 /// Initialize the application: open onboarding or main windows based on DB state.
 async fn initialize_app(app: tauri::AppHandle) {
-    let state = app.state::<Mutex<AppState>>();
-    let needs_onboarding = {
-        let state = state.lock().unwrap();
-        state.needs_onboarding()
-    };
+    let state = app.state::<AppState>();
+    let needs_onboarding = state.needs_onboarding().unwrap_or(true);
 
     if needs_onboarding {
         if let Some(onboarding) = app.get_webview_window("onboarding") {
@@ -69,15 +72,13 @@ async fn initialize_app(app: tauri::AppHandle) {
             let _ = main.set_focus();
         }
         // Show widget based on preferences
-        let show_widget = {
-            let state = state.lock().unwrap();
-            state
-                .settings
+        let show_widget = state.settings().is_ok_and(|settings| {
+            settings
                 .preferences
                 .as_ref()
                 .and_then(|p| p.show_widget_while_inactive)
                 .unwrap_or(false)
-        };
+        });
         if show_widget {
             if let Some(widget) = app.get_webview_window("widget") {
                 let _ = widget.show();
@@ -89,6 +90,9 @@ async fn initialize_app(app: tauri::AppHandle) {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let db = db::Database::new().expect("Failed to initialize database");
+    if let Err(e) = model_files::catalog::seed(&db) {
+        log::warn!("Failed to seed model catalog: {e}");
+    }
     let app_state = AppState::new(db);
 
     tauri::Builder::default()
@@ -102,7 +106,7 @@ pub fn run() {
             tauri_plugin_autostart::MacosLauncher::LaunchAgent,
             Some(vec!["--autostart"]),
         ))
-        .manage(Mutex::new(app_state))
+        .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             commands::settings::get_settings,
             commands::settings::update_settings,
@@ -119,21 +123,46 @@ pub fn run() {
             commands::settings::get_preferences,
             commands::settings::set_preferences,
             commands::settings::sync_auto_launch,
+            commands::settings::get_text_normalization_settings,
+            commands::settings::set_text_normalization_settings,
+            commands::settings::get_itn_settings,
+            commands::settings::set_itn_settings,
             commands::transcriptions::get_transcriptions,
             commands::transcriptions::get_transcription,
+            commands::transcriptions::get_transcription_segments,
             commands::transcriptions::delete_transcription,
             commands::transcriptions::delete_all_transcriptions,
             commands::transcriptions::save_transcription,
+            commands::vocabulary::get_vocabulary,
+            commands::vocabulary::add_vocabulary_term,
+            commands::vocabulary::remove_vocabulary_term,
+            commands::vocabulary::import_vocabulary,
+            commands::text_rules::get_text_rules,
+            commands::text_rules::add_text_rule,
+            commands::text_rules::update_text_rule,
+            commands::text_rules::reorder_text_rules,
+            commands::text_rules::delete_text_rule,
             commands::recording::signal_start,
+            commands::recording::signal_pause,
+            commands::recording::signal_resume,
             commands::recording::signal_stop,
             commands::recording::get_recording_state,
             commands::recording::process_audio_chunk,
             commands::recording::finalize_session,
             commands::recording::cancel_session,
+            commands::file_transcription::transcribe_file,
+            commands::file_transcription::transcribe_files,
+            commands::file_transcription::cancel_file_transcription,
+            commands::file_transcription::get_supported_audio_extensions,
             commands::models::get_models,
             commands::models::get_selected_model,
             commands::models::select_model,
             commands::models::delete_model,
+            commands::models::download_model,
+            commands::models::import_model,
+            commands::models::scan_model_files,
+            commands::models::reconcile_model_files,
+            commands::models::verify_models,
             commands::models::save_model,
             commands::widget::show_widget,
             commands::widget::hide_widget,
@@ -150,6 +179,31 @@ pub fn run() {
             // Initialize system tray
             build_tray(app.handle())?;
 
+            // Start warming the speech model while the windows come up
+            let state = app.state::<AppState>();
+            state.models.attach(app.handle().clone());
+            if let Err(e) = state.preload_speech_model() {
+                log::warn!("Failed to preload speech model: {e}");
+            }
+
+            // Report model files and rows that have drifted apart, then
+            // re-hash the files modified since they were last verified
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let state = handle.state::<AppState>();
+                match commands::models::scan_model_files(state) {
+                    Ok(report) if !report.is_clean() => {
+                        log::warn!("Model files out of sync with the database: {report:?}");
+                        let _ = handle.emit("model-files-report", &report);
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Failed to scan model files: {e}"),
+                }
+                if let Err(e) = commands::models::verify_model_files(&handle, false) {
+                    log::warn!("Failed to verify model files: {e}");
+                }
+            });
+
             // Initialize app asynchronously
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
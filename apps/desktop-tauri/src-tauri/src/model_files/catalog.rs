@@ -0,0 +1,154 @@
+//! Speech models the app offers for download, embedded from `catalog.json`
+//! and synced into `models` on every start.
+
+use crate::db::{Database, Model};
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Provider of every catalog model in the `models` table.
+pub const CATALOG_PROVIDER: &str = "local-whisper";
+
+#[derive(Debug, Deserialize)]
+pub struct Catalog {
+    pub models: Vec<CatalogModel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogModel {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Approximate download size, for display.
+    pub size: String,
+    pub url: String,
+    /// Hex SHA-1 of the file, where published.
+    pub checksum: Option<String>,
+    /// Why there is no `checksum`. Such a file is downloaded unchecked, and
+    /// the SHA-1 of the download is recorded for later verification.
+    #[serde(default)]
+    pub unverified: Option<String>,
+    pub speed: f64,
+    pub accuracy: f64,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+pub fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| {
+        serde_json::from_str(include_str!("catalog.json")).expect("invalid embedded model catalog")
+    })
+}
+
+pub fn find(id: &str) -> Option<&'static CatalogModel> {
+    catalog().models.iter().find(|m| m.id == id)
+}
+
+/// Bring the catalog rows in `models` up to date, keeping download records.
+pub fn seed(db: &Database) -> rusqlite::Result<()> {
+    let models: Vec<Model> = catalog()
+        .models
+        .iter()
+        .map(CatalogModel::to_model)
+        .collect();
+    db.sync_catalog_models(CATALOG_PROVIDER, &models)?;
+    log::info!("Model catalog seeded with {} models", models.len());
+    Ok(())
+}
+
+impl CatalogModel {
+    pub fn to_model(&self) -> Model {
+        Model {
+            size: Some(self.size.clone()),
+            description: Some(self.description.clone()),
+            checksum: self.checksum.clone(),
+            speed: Some(self.speed),
+            accuracy: Some(self.accuracy),
+            ..Model::speech(&self.id, CATALOG_PROVIDER, &self.name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn embedded_catalog_is_valid() {
+        let catalog = catalog();
+        let mut ids = HashSet::new();
+        for model in &catalog.models {
+            assert!(ids.insert(model.id.as_str()), "duplicate id {}", model.id);
+            assert!(model.url.starts_with("https://"), "{}", model.id);
+            match (&model.checksum, &model.unverified) {
+                (Some(checksum), None) => {
+                    assert_eq!(checksum.len(), 40, "{}", model.id);
+                    assert!(checksum.chars().all(|c| c.is_ascii_hexdigit()));
+                }
+                (None, Some(_)) => {}
+                _ => panic!("{} needs either a checksum or a reason", model.id),
+            }
+        }
+        assert!(find("whisper-large-v3-turbo").is_some());
+    }
+
+    #[test]
+    fn reseeding_keeps_download_records() {
+        let db =
+            Database::with_connection(rusqlite::Connection::open_in_memory().unwrap()).unwrap();
+        seed(&db).unwrap();
+        for id in ["whisper-tiny", "whisper-small"] {
+            let downloaded = Model {
+                local_path: Some(format!("/models/{id}.bin")),
+                size_bytes: Some(1234),
+                checksum: Some("f".repeat(40)),
+                downloaded_at: Some(1_700_000_000),
+                ..find(id).unwrap().to_model()
+            };
+            db.save_model(&downloaded).unwrap();
+        }
+
+        // A newer catalog rewords tiny and drops base and small.
+        let mut models: Vec<Model> = catalog()
+            .models
+            .iter()
+            .filter(|m| m.id != "whisper-base" && m.id != "whisper-small")
+            .map(CatalogModel::to_model)
+            .collect();
+        let tiny = models.iter_mut().find(|m| m.id == "whisper-tiny").unwrap();
+        tiny.description = Some("Reworded".to_string());
+        tiny.speed = Some(4.5);
+        db.sync_catalog_models(CATALOG_PROVIDER, &models).unwrap();
+
+        let tiny = db
+            .get_model("whisper-tiny", CATALOG_PROVIDER)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tiny.local_path.as_deref(), Some("/models/whisper-tiny.bin"));
+        assert_eq!(tiny.downloaded_at, Some(1_700_000_000));
+        assert_eq!(tiny.checksum, Some("f".repeat(40)));
+        assert_eq!(tiny.description.as_deref(), Some("Reworded"));
+        assert_eq!(tiny.speed, Some(4.5));
+        assert!(db
+            .get_model("whisper-base", CATALOG_PROVIDER)
+            .unwrap()
+            .is_none());
+        let small = db
+            .get_model("whisper-small", CATALOG_PROVIDER)
+            .unwrap()
+            .unwrap();
+        assert_eq!(small.downloaded_at, Some(1_700_000_000));
+
+        // Seeding the embedded catalog again restores base.
+        seed(&db).unwrap();
+        assert!(db
+            .get_model("whisper-base", CATALOG_PROVIDER)
+            .unwrap()
+            .is_some());
+        let tiny = db
+            .get_model("whisper-tiny", CATALOG_PROVIDER)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tiny.local_path.as_deref(), Some("/models/whisper-tiny.bin"));
+    }
+}
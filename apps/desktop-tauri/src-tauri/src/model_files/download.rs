@@ -0,0 +1,356 @@
+//! Resumable model downloads. Bytes go to `<name>.part` next to the
+//! destination, which a later attempt continues with an HTTP Range request;
+//! the file only takes its final name once its checksum has been checked.
+
+use crate::model_files::sha1_file;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::{Response, StatusCode};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// A finished download.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadedFile {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Hex SHA-1 of the file.
+    pub checksum: String,
+}
+
+/// Fetch `url` into `dest`, continuing a partial download left by an earlier
+/// attempt. `on_progress` gets the bytes on disk and the total size, when
+/// known. A checksum mismatch discards the download.
+pub async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    expected_sha1: Option<&str>,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<DownloadedFile, String> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    let part = part_path(dest);
+    let mut offset = tokio::fs::metadata(&part)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        log::info!("Resuming download of {url} at byte {offset}");
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    let mut response = request.send().await.map_err(|e| e.to_string())?;
+    let total = match response.status() {
+        StatusCode::PARTIAL_CONTENT => match content_range(&response) {
+            Some((start, total)) if start == offset => total,
+            _ => return Err(format!("Unexpected Content-Range from {url}")),
+        },
+        // The server ignored the range, so start over.
+        StatusCode::OK => {
+            offset = 0;
+            response.content_length()
+        }
+        // The partial file already holds the whole model.
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => Some(offset),
+        status => return Err(format!("Download of {url} failed: HTTP {status}")),
+    };
+    on_progress(offset, total);
+
+    if offset < total.unwrap_or(u64::MAX) {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&part)
+            .await
+            .map_err(|e| e.to_string())?;
+        let received = async {
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+                offset += chunk.len() as u64;
+                on_progress(offset, total);
+            }
+            Ok::<_, String>(())
+        }
+        .await;
+        // A failed read keeps what has arrived for the next attempt, once
+        // the write still in flight has landed.
+        file.flush().await.map_err(|e| e.to_string())?;
+        received?;
+        file.sync_all().await.map_err(|e| e.to_string())?;
+    }
+    if let Some(total) = total.filter(|&total| total != offset) {
+        return Err(format!(
+            "Download of {url} ended at {offset} of {total} bytes"
+        ));
+    }
+
+    let checksum = {
+        let part = part.clone();
+        tokio::task::spawn_blocking(move || sha1_file(&part))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?
+    };
+    if let Some(expected) = expected_sha1.filter(|e| !e.eq_ignore_ascii_case(&checksum)) {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(format!(
+            "Checksum mismatch for {url}: expected {expected}, got {checksum}"
+        ));
+    }
+    tokio::fs::rename(&part, dest)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(DownloadedFile {
+        path: dest.to_path_buf(),
+        size_bytes: offset,
+        checksum,
+    })
+}
+
+/// Where the download for `dest` is kept until it completes.
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// First byte and total size from a `Content-Range: bytes a-b/total` header.
+fn content_range(response: &Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_files::test_dir;
+    use sha1::{Digest, Sha1};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// Minimal HTTP/1.1 server for one file. It records each request's
+    /// `Range` header and can cut the first response short to simulate a
+    /// dropped connection.
+    struct FileServer {
+        url: String,
+        ranges: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    impl FileServer {
+        fn start(body: Vec<u8>, honor_range: bool, cut_first_at: Option<usize>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/ggml-test.bin", listener.local_addr().unwrap());
+            let ranges = Arc::new(Mutex::new(Vec::new()));
+            let seen = ranges.clone();
+            std::thread::spawn(move || {
+                let mut cut = cut_first_at;
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let range = read_range(&mut BufReader::new(&stream));
+                    seen.lock().unwrap().push(range.clone());
+                    let start = range
+                        .filter(|_| honor_range)
+                        .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok());
+                    let len = body.len();
+                    let (head, bytes) = match start {
+                        Some(start) if start >= len => (
+                            format!(
+                                "HTTP/1.1 416 Range Not Satisfiable\r\n\
+                                 Content-Range: bytes */{len}\r\nContent-Length: 0\r\n"
+                            ),
+                            &body[..0],
+                        ),
+                        Some(start) => (
+                            format!(
+                                "HTTP/1.1 206 Partial Content\r\n\
+                                 Content-Range: bytes {start}-{}/{len}\r\nContent-Length: {}\r\n",
+                                len - 1,
+                                len - start
+                            ),
+                            &body[start..],
+                        ),
+                        None => (
+                            format!("HTTP/1.1 200 OK\r\nContent-Length: {len}\r\n"),
+                            &body[..],
+                        ),
+                    };
+                    let bytes = match cut.take() {
+                        Some(at) => &bytes[..at],
+                        None => bytes,
+                    };
+                    let _ = stream.write_all(format!("{head}Connection: close\r\n\r\n").as_bytes());
+                    let _ = stream.write_all(bytes);
+                }
+            });
+            Self { url, ranges }
+        }
+
+        fn ranges(&self) -> Vec<Option<String>> {
+            self.ranges.lock().unwrap().clone()
+        }
+    }
+
+    fn read_range(reader: &mut impl BufRead) -> Option<String> {
+        let mut range = None;
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("range") {
+                    range = Some(value.trim().to_string());
+                }
+            }
+            line.clear();
+        }
+        range
+    }
+
+    fn model_bytes() -> Vec<u8> {
+        (0..200_000u32).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    fn sha1(bytes: &[u8]) -> String {
+        format!("{:x}", Sha1::digest(bytes))
+    }
+
+    /// Destination in a directory `download` has to create.
+    fn temp_dest(dir: &TempDir) -> PathBuf {
+        dir.path().join("models").join("ggml-test.bin")
+    }
+
+    async fn fetch(server: &FileServer, dest: &Path, sha1: &str) -> Result<DownloadedFile, String> {
+        download(
+            &reqwest::Client::new(),
+            &server.url,
+            dest,
+            Some(sha1),
+            |_, _| {},
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn downloads_and_verifies() {
+        let body = model_bytes();
+        let server = FileServer::start(body.clone(), true, None);
+        let dir = test_dir();
+        let dest = temp_dest(&dir);
+        let mut progress = Vec::new();
+
+        let file = download(
+            &reqwest::Client::new(),
+            &server.url,
+            &dest,
+            Some(&sha1(&body).to_uppercase()),
+            |done, total| progress.push((done, total)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(file.size_bytes, body.len() as u64);
+        assert_eq!(file.checksum, sha1(&body));
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert!(!part_path(&dest).exists());
+        assert_eq!(progress.first(), Some(&(0, Some(body.len() as u64))));
+        assert_eq!(
+            progress.last(),
+            Some(&(body.len() as u64, Some(body.len() as u64)))
+        );
+        assert_eq!(server.ranges(), [None]);
+    }
+
+    #[tokio::test]
+    async fn resumes_after_dropped_connection() {
+        let body = model_bytes();
+        let server = FileServer::start(body.clone(), true, Some(50_000));
+        let dir = test_dir();
+        let dest = temp_dest(&dir);
+
+        assert!(fetch(&server, &dest, &sha1(&body)).await.is_err());
+        assert_eq!(std::fs::metadata(part_path(&dest)).unwrap().len(), 50_000);
+        assert!(!dest.exists());
+
+        let file = fetch(&server, &dest, &sha1(&body)).await.unwrap();
+        assert_eq!(file.size_bytes, body.len() as u64);
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert_eq!(server.ranges(), [None, Some("bytes=50000-".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn restarts_when_range_is_ignored() {
+        let body = model_bytes();
+        let server = FileServer::start(body.clone(), false, None);
+        let dir = test_dir();
+        let dest = temp_dest(&dir);
+        std::fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        std::fs::write(part_path(&dest), b"stale bytes").unwrap();
+
+        fetch(&server, &dest, &sha1(&body)).await.unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert_eq!(server.ranges(), [Some("bytes=11-".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn completes_from_full_partial_file() {
+        let body = model_bytes();
+        let server = FileServer::start(body.clone(), true, None);
+        let dir = test_dir();
+        let dest = temp_dest(&dir);
+        std::fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        std::fs::write(part_path(&dest), &body).unwrap();
+
+        let file = fetch(&server, &dest, &sha1(&body)).await.unwrap();
+        assert_eq!(file.size_bytes, body.len() as u64);
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn rejects_checksum_mismatch() {
+        let body = model_bytes();
+        let server = FileServer::start(body, true, None);
+        let dir = test_dir();
+        let dest = temp_dest(&dir);
+
+        let err = fetch(&server, &dest, &sha1(b"something else"))
+            .await
+            .unwrap_err();
+        assert!(err.contains("Checksum mismatch"), "{err}");
+        assert!(!dest.exists());
+        assert!(!part_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn reports_http_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_range(&mut BufReader::new(&stream));
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+        });
+        let dir = test_dir();
+        let dest = temp_dest(&dir);
+
+        let err = download(
+            &reqwest::Client::new(),
+            &format!("http://{addr}/missing.bin"),
+            &dest,
+            None,
+            |_, _| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("404"), "{err}");
+        assert!(!part_path(&dest).exists());
+    }
+}
@@ -0,0 +1,363 @@
+//! Importing whisper models the user converted themselves. The header is
+//! checked the way whisper.cpp reads it, so a file that would fail to load is
+//! rejected up front with a specific reason.
+
+use crate::model_files::{download::part_path, sanitize_file_name, sha1_file};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Provider of imported models in the `models` table.
+pub const IMPORT_PROVIDER: &str = "local-import";
+
+/// `GGML_FILE_MAGIC`, "ggml" as a little-endian u32.
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+/// "GGUF" as a little-endian u32.
+const GGUF_MAGIC: u32 = 0x4655_4747;
+/// The quantization version is stored in `ftype` in multiples of this.
+const QNT_VERSION_FACTOR: i32 = 1000;
+/// FFT bins per mel filter in every whisper model.
+const MEL_FFT_BINS: i32 = 201;
+/// Upper bound for any single dimension, to catch garbage headers.
+const MAX_DIMENSION: i32 = 1 << 16;
+
+/// Hyperparameters from a ggml whisper model header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GgmlHeader {
+    pub n_vocab: i32,
+    pub n_audio_ctx: i32,
+    pub n_audio_state: i32,
+    pub n_audio_head: i32,
+    pub n_audio_layer: i32,
+    pub n_text_ctx: i32,
+    pub n_text_state: i32,
+    pub n_text_head: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+    /// Weight type, without the quantization version.
+    pub ftype: i32,
+}
+
+impl GgmlHeader {
+    /// Whisper size this model was derived from, by encoder depth.
+    pub fn size_name(&self) -> &'static str {
+        match self.n_audio_layer {
+            4 => "tiny",
+            6 => "base",
+            12 => "small",
+            24 => "medium",
+            32 => "large",
+            _ => "custom",
+        }
+    }
+
+    /// Name of the weight type, or `None` if whisper.cpp cannot load it.
+    pub fn ftype_name(&self) -> Option<&'static str> {
+        Some(match self.ftype {
+            0 => "f32",
+            1 => "f16",
+            2 => "q4_0",
+            3 => "q4_1",
+            7 => "q8_0",
+            8 => "q5_0",
+            9 => "q5_1",
+            10 => "q2_k",
+            11 => "q3_k",
+            12 => "q4_k",
+            13 => "q5_k",
+            14 => "q6_k",
+            15..=23 => "iq",
+            24 => "bf16",
+            25 => "mxfp4",
+            _ => return None,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "Imported {} model ({}, {} encoder / {} decoder layers, {} mel bins)",
+            self.size_name(),
+            self.ftype_name().unwrap_or("unknown"),
+            self.n_audio_layer,
+            self.n_text_layer,
+            self.n_mels
+        )
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let dimensions = [
+            ("n_vocab", self.n_vocab),
+            ("n_audio_ctx", self.n_audio_ctx),
+            ("n_audio_state", self.n_audio_state),
+            ("n_audio_head", self.n_audio_head),
+            ("n_audio_layer", self.n_audio_layer),
+            ("n_text_ctx", self.n_text_ctx),
+            ("n_text_state", self.n_text_state),
+            ("n_text_head", self.n_text_head),
+            ("n_text_layer", self.n_text_layer),
+        ];
+        for (name, value) in dimensions {
+            if !(1..=MAX_DIMENSION).contains(&value) {
+                return Err(format!("Invalid model dimensions: {name} is {value}"));
+            }
+        }
+        if self.n_audio_state % self.n_audio_head != 0 || self.n_text_state % self.n_text_head != 0
+        {
+            return Err("Invalid model dimensions: state size is not divisible by heads".into());
+        }
+        if !matches!(self.n_mels, 80 | 128) {
+            return Err(format!(
+                "Invalid model dimensions: {} mel bins (expected 80 or 128)",
+                self.n_mels
+            ));
+        }
+        if self.ftype_name().is_none() {
+            return Err(format!("Unsupported weight type {}", self.ftype));
+        }
+        Ok(())
+    }
+}
+
+/// A model file placed in the models directory.
+#[derive(Debug, Clone)]
+pub struct ImportedFile {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Hex SHA-1 of the file.
+    pub checksum: String,
+    pub header: GgmlHeader,
+}
+
+/// Read and check the header of the whisper model at `path`.
+pub fn read_header(path: &Path) -> Result<GgmlHeader, String> {
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    read_header_from(BufReader::new(file))
+}
+
+fn read_header_from(mut reader: impl Read) -> Result<GgmlHeader, String> {
+    let truncated = |_: std::io::Error| "File is too short to be a whisper model".to_string();
+    let mut next = || -> std::io::Result<i32> {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)?;
+        Ok(i32::from_le_bytes(buf))
+    };
+
+    match next().map_err(truncated)? as u32 {
+        GGML_MAGIC => {}
+        GGUF_MAGIC => {
+            return Err(
+                "GGUF files are not supported by whisper.cpp; convert the model to ggml".into(),
+            )
+        }
+        _ => return Err("Not a ggml model (bad magic)".into()),
+    }
+    let mut header = GgmlHeader {
+        n_vocab: next().map_err(truncated)?,
+        n_audio_ctx: next().map_err(truncated)?,
+        n_audio_state: next().map_err(truncated)?,
+        n_audio_head: next().map_err(truncated)?,
+        n_audio_layer: next().map_err(truncated)?,
+        n_text_ctx: next().map_err(truncated)?,
+        n_text_state: next().map_err(truncated)?,
+        n_text_head: next().map_err(truncated)?,
+        n_text_layer: next().map_err(truncated)?,
+        n_mels: next().map_err(truncated)?,
+        ftype: next().map_err(truncated)?,
+    };
+    header.ftype %= QNT_VERSION_FACTOR;
+    header.validate()?;
+
+    // The mel filter bank follows the hyperparameters.
+    let filter_mels = next().map_err(truncated)?;
+    let filter_bins = next().map_err(truncated)?;
+    if filter_mels != header.n_mels || filter_bins != MEL_FFT_BINS {
+        return Err(format!(
+            "Invalid mel filters: {filter_mels}x{filter_bins} for a {}-mel model",
+            header.n_mels
+        ));
+    }
+    Ok(header)
+}
+
+/// Validate `source` and place it in `dir` under a name not yet taken,
+/// hard-linking when possible and copying otherwise.
+pub fn import(source: &Path, dir: &Path) -> Result<ImportedFile, String> {
+    let header = read_header(source)?;
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    let stem = source
+        .file_stem()
+        .and_then(|s| sanitize_file_name(&s.to_string_lossy()))
+        .unwrap_or_else(|| "imported-model".to_string());
+    let dest = (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{stem}.bin")),
+            n => dir.join(format!("{stem}-{n}.bin")),
+        })
+        .find(|path| !path.exists())
+        .expect("unbounded range");
+
+    if std::fs::hard_link(source, &dest).is_err() {
+        // Copy under a temporary name so a partial copy is never picked up.
+        let part = part_path(&dest);
+        std::fs::copy(source, &part).map_err(|e| format!("Failed to copy model: {e}"))?;
+        std::fs::rename(&part, &dest).map_err(|e| e.to_string())?;
+    }
+
+    let result =
+        std::fs::metadata(&dest).and_then(|metadata| Ok((metadata.len(), sha1_file(&dest)?)));
+    match result {
+        Ok((size_bytes, checksum)) => Ok(ImportedFile {
+            path: dest,
+            size_bytes,
+            checksum,
+            header,
+        }),
+        Err(e) => {
+            let _ = std::fs::remove_file(&dest);
+            Err(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_files::test_dir;
+
+    /// Header of ggml-large-v3-turbo.
+    const TURBO: GgmlHeader = GgmlHeader {
+        n_vocab: 51866,
+        n_audio_ctx: 1500,
+        n_audio_state: 1280,
+        n_audio_head: 20,
+        n_audio_layer: 32,
+        n_text_ctx: 448,
+        n_text_state: 1280,
+        n_text_head: 20,
+        n_text_layer: 4,
+        n_mels: 128,
+        ftype: 1,
+    };
+
+    fn encode(magic: u32, header: &GgmlHeader, filters: (i32, i32)) -> Vec<u8> {
+        let values = [
+            header.n_vocab,
+            header.n_audio_ctx,
+            header.n_audio_state,
+            header.n_audio_head,
+            header.n_audio_layer,
+            header.n_text_ctx,
+            header.n_text_state,
+            header.n_text_head,
+            header.n_text_layer,
+            header.n_mels,
+            header.ftype,
+            filters.0,
+            filters.1,
+        ];
+        let mut bytes = magic.to_le_bytes().to_vec();
+        for value in values {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn parse(bytes: Vec<u8>) -> Result<GgmlHeader, String> {
+        read_header_from(bytes.as_slice())
+    }
+
+    #[test]
+    fn reads_valid_header() {
+        let header = parse(encode(GGML_MAGIC, &TURBO, (128, 201))).unwrap();
+        assert_eq!(header, TURBO);
+        assert_eq!(header.size_name(), "large");
+        assert_eq!(header.ftype_name(), Some("f16"));
+    }
+
+    #[test]
+    fn strips_quantization_version() {
+        let quantized = GgmlHeader {
+            ftype: 2008,
+            ..TURBO
+        };
+        let header = parse(encode(GGML_MAGIC, &quantized, (128, 201))).unwrap();
+        assert_eq!(header.ftype_name(), Some("q5_0"));
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let cases = [
+            (encode(0x1234_5678, &TURBO, (128, 201)), "bad magic"),
+            (encode(GGUF_MAGIC, &TURBO, (128, 201)), "GGUF"),
+            (
+                encode(GGML_MAGIC, &TURBO, (128, 201))[..20].to_vec(),
+                "too short",
+            ),
+            (
+                encode(
+                    GGML_MAGIC,
+                    &GgmlHeader {
+                        n_audio_layer: 0,
+                        ..TURBO
+                    },
+                    (128, 201),
+                ),
+                "n_audio_layer is 0",
+            ),
+            (
+                encode(
+                    GGML_MAGIC,
+                    &GgmlHeader {
+                        n_audio_head: 7,
+                        ..TURBO
+                    },
+                    (128, 201),
+                ),
+                "divisible",
+            ),
+            (
+                encode(
+                    GGML_MAGIC,
+                    &GgmlHeader {
+                        n_mels: 64,
+                        ..TURBO
+                    },
+                    (64, 201),
+                ),
+                "64 mel bins",
+            ),
+            (
+                encode(GGML_MAGIC, &GgmlHeader { ftype: 4, ..TURBO }, (128, 201)),
+                "Unsupported weight type 4",
+            ),
+            (encode(GGML_MAGIC, &TURBO, (80, 201)), "mel filters"),
+        ];
+        for (bytes, expected) in cases {
+            let err = parse(bytes).unwrap_err();
+            assert!(
+                err.contains(expected),
+                "{err:?} should mention {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn imports_under_a_free_name() {
+        let tmp = test_dir();
+        let dir = tmp.path();
+        let source = dir.join("my model.bin");
+        let mut bytes = encode(GGML_MAGIC, &TURBO, (128, 201));
+        bytes.extend([0u8; 64]);
+        std::fs::write(&source, &bytes).unwrap();
+        let models = dir.join("models");
+
+        let first = import(&source, &models).unwrap();
+        let second = import(&source, &models).unwrap();
+        assert_eq!(first.path, models.join("my_model.bin"));
+        assert_eq!(second.path, models.join("my_model-2.bin"));
+        assert_eq!(first.size_bytes, bytes.len() as u64);
+        assert_eq!(first.checksum, sha1_file(&source).unwrap());
+        assert_eq!(std::fs::read(&second.path).unwrap(), bytes);
+    }
+}
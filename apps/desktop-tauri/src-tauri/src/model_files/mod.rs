@@ -0,0 +1,58 @@
+//! Speech model files on disk, kept in the app data directory next to the
+//! database.
+
+pub mod catalog;
+pub mod download;
+pub mod import;
+pub mod reconcile;
+pub mod verify;
+
+use sha1::{Digest, Sha1};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Directory holding downloaded and imported model files.
+pub fn models_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("dev.piriwata.kotoba")
+        .join("models")
+}
+
+/// Hex SHA-1 of the file at `path`, the format of `Model.checksum`.
+pub fn sha1_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Empty directory for a test, removed when dropped.
+#[cfg(test)]
+pub(crate) fn test_dir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("kotoba-")
+        .tempdir()
+        .expect("failed to create a temp dir")
+}
+
+/// A file name safe to create in [`models_dir`], or `None` if nothing usable
+/// is left of `name`.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let name = name.trim_start_matches('.');
+    (!name.is_empty()).then(|| name.to_string())
+}
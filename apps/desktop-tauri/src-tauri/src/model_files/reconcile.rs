@@ -0,0 +1,149 @@
+//! Keeping the models directory and the `models` table in agreement: files
+//! no row refers to, and rows whose file has gone.
+
+use crate::db::Model;
+use crate::model_files::download::part_path;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelFilesReport {
+    /// Files in the models directory that no model refers to.
+    pub orphan_files: Vec<String>,
+    /// Downloaded models whose file no longer exists.
+    pub missing_files: Vec<MissingModelFile>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingModelFile {
+    pub id: String,
+    pub provider: String,
+    pub local_path: String,
+}
+
+impl ModelFilesReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphan_files.is_empty() && self.missing_files.is_empty()
+    }
+}
+
+/// Compare `models` with the files in `dir`. Partial downloads are not
+/// orphans, since retrying the download resumes them.
+pub fn scan(models: &[Model], dir: &Path) -> ModelFilesReport {
+    let mut report = ModelFilesReport::default();
+    let mut referenced = HashSet::new();
+    for model in models {
+        let Some(local_path) = model.local_path.as_deref() else {
+            continue;
+        };
+        let path = Path::new(local_path);
+        if path.is_file() {
+            referenced.insert(canonical(path));
+        } else {
+            report.missing_files.push(MissingModelFile {
+                id: model.id.clone(),
+                provider: model.provider.clone(),
+                local_path: local_path.to_string(),
+            });
+        }
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return report;
+    };
+    let mut orphans: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_none_or(|ext| ext != "part"))
+        .filter(|path| !referenced.contains(&canonical(path)))
+        .map(|path| path.display().to_string())
+        .collect();
+    orphans.sort();
+    report.orphan_files = orphans;
+    report
+}
+
+/// Delete the model file at `path` and any partial download of it, but only
+/// inside `dir`; files the user keeps elsewhere are never touched. Returns
+/// whether a file was removed.
+pub fn remove_model_file(path: &Path, dir: &Path) -> Result<bool, String> {
+    if !canonical(path).starts_with(canonical(dir)) {
+        log::info!("Keeping {} outside the models directory", path.display());
+        return Ok(false);
+    }
+    let _ = std::fs::remove_file(part_path(path));
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete {}: {e}", path.display())),
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_files::test_dir;
+
+    fn model(id: &str, local_path: Option<&Path>) -> Model {
+        Model {
+            local_path: local_path.map(|p| p.display().to_string()),
+            ..Model::speech(id, "local-whisper", id)
+        }
+    }
+
+    #[test]
+    fn reports_orphans_and_missing_files() {
+        let tmp = test_dir();
+        let dir = tmp.path();
+        for name in ["ggml-tiny.bin", "ggml-old.bin", "ggml-base.bin.part"] {
+            std::fs::write(dir.join(name), b"ggml").unwrap();
+        }
+        let models = [
+            model("whisper-tiny", Some(&dir.join("ggml-tiny.bin"))),
+            model("small", Some(&dir.join("ggml-small.bin"))),
+            model("whisper-base", None),
+        ];
+
+        let report = scan(&models, dir);
+        assert_eq!(
+            report.orphan_files,
+            [dir.join("ggml-old.bin").display().to_string()]
+        );
+        assert_eq!(
+            report.missing_files,
+            [MissingModelFile {
+                id: "small".to_string(),
+                provider: "local-whisper".to_string(),
+                local_path: dir.join("ggml-small.bin").display().to_string(),
+            }]
+        );
+        assert!(!report.is_clean());
+        assert!(scan(&models[..1], &dir.join("absent")).is_clean());
+    }
+
+    #[test]
+    fn removes_only_files_inside_the_models_dir() {
+        let tmp = test_dir();
+        let dir = tmp.path();
+        let models = dir.join("models");
+        std::fs::create_dir_all(&models).unwrap();
+        let inside = models.join("ggml-tiny.bin");
+        let outside = dir.join("ggml-mine.bin");
+        std::fs::write(&inside, b"ggml").unwrap();
+        std::fs::write(part_path(&inside), b"gg").unwrap();
+        std::fs::write(&outside, b"ggml").unwrap();
+
+        assert_eq!(remove_model_file(&inside, &models), Ok(true));
+        assert!(!inside.exists());
+        assert!(!part_path(&inside).exists());
+        assert_eq!(remove_model_file(&inside, &models), Ok(false));
+        assert_eq!(remove_model_file(&outside, &models), Ok(false));
+        assert!(outside.exists());
+    }
+}
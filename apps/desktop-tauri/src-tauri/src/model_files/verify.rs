@@ -0,0 +1,123 @@
+//! Integrity checks of model files against the size and SHA-1 recorded when
+//! they were downloaded or imported.
+
+use crate::db::Model;
+use crate::model_files::sha1_file;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Verified,
+    /// The file does not match, for the given reason.
+    Corrupt(String),
+}
+
+/// Check the file of `model`. Returns `None` when there is nothing to check:
+/// no file or checksum, or unless `all`, a file not modified since its last
+/// check. A missing file is an error of kind `NotFound`.
+pub fn check(model: &Model, all: bool) -> std::io::Result<Option<Outcome>> {
+    let (Some(local_path), Some(checksum)) =
+        (model.local_path.as_deref(), model.checksum.as_deref())
+    else {
+        return Ok(None);
+    };
+    let path = Path::new(local_path);
+    let metadata = std::fs::metadata(path)?;
+    if !all && model.status.is_some() && !modified_since(&metadata, model.verified_at) {
+        return Ok(None);
+    }
+
+    if let Some(expected) = model.size_bytes {
+        if metadata.len() != expected as u64 {
+            return Ok(Some(Outcome::Corrupt(format!(
+                "File is {} bytes, expected {expected}",
+                metadata.len()
+            ))));
+        }
+    }
+    let actual = sha1_file(path)?;
+    if actual.eq_ignore_ascii_case(checksum) {
+        Ok(Some(Outcome::Verified))
+    } else {
+        Ok(Some(Outcome::Corrupt(format!(
+            "SHA-1 is {actual}, expected {checksum}"
+        ))))
+    }
+}
+
+/// Whether the file may have been modified after unix time `verified_at`.
+/// Times only have second precision, so a change in the same second counts,
+/// as does an unknown time.
+fn modified_since(metadata: &std::fs::Metadata, verified_at: Option<i64>) -> bool {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+    match (modified, verified_at) {
+        (Some(modified), Some(verified_at)) => modified.as_secs() as i64 >= verified_at,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ModelStatus;
+    use crate::model_files::test_dir;
+
+    fn model(path: &Path, contents: &[u8]) -> Model {
+        std::fs::write(path, contents).unwrap();
+        Model {
+            local_path: Some(path.display().to_string()),
+            size_bytes: Some(contents.len() as i64),
+            checksum: Some(sha1_file(path).unwrap()),
+            ..Model::speech("whisper-tiny", "local-whisper", "tiny")
+        }
+    }
+
+    #[test]
+    fn detects_truncated_and_altered_files() {
+        let dir = test_dir();
+        let path = dir.path().join("ggml-tiny.bin");
+        let model = model(&path, b"ggml weights");
+
+        assert_eq!(check(&model, false).unwrap(), Some(Outcome::Verified));
+
+        std::fs::write(&path, b"ggml").unwrap();
+        let outcome = check(&model, false).unwrap();
+        assert!(matches!(outcome, Some(Outcome::Corrupt(reason)) if reason.contains("bytes")));
+
+        std::fs::write(&path, b"ggml WEIGHTS").unwrap();
+        let outcome = check(&model, false).unwrap();
+        assert!(matches!(outcome, Some(Outcome::Corrupt(reason)) if reason.contains("SHA-1")));
+
+        std::fs::remove_file(&path).unwrap();
+        let err = check(&model, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn skips_files_unchanged_since_last_check() {
+        let dir = test_dir();
+        let mut model = model(&dir.path().join("ggml-tiny.bin"), b"ggml weights");
+        model.status = Some(ModelStatus::Verified);
+        model.verified_at = Some(i64::MAX);
+
+        assert_eq!(check(&model, false).unwrap(), None);
+        assert_eq!(check(&model, true).unwrap(), Some(Outcome::Verified));
+
+        model.verified_at = Some(0);
+        assert_eq!(check(&model, false).unwrap(), Some(Outcome::Verified));
+
+        // Modified in the second it was checked: it may have changed after.
+        let path = model.local_path.as_deref().unwrap();
+        let modified = std::fs::metadata(path).unwrap().modified().unwrap();
+        let modified = modified.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        model.verified_at = Some(modified);
+        assert_eq!(check(&model, false).unwrap(), Some(Outcome::Verified));
+
+        model.checksum = None;
+        assert_eq!(check(&model, true).unwrap(), None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Keeps the speech model loaded between transcriptions. Loading a medium
+//! model takes seconds on a CPU-only machine, which every dictation would
+//! otherwise pay on finalize.
+//!
+//! The last model used stays loaded until it has been idle for the configured
+//! timeout. With `TranscriptionSettings.preload_whisper_model`, the selected
+//! model is also loaded ahead of time, at startup and whenever it changes.
+
+use crate::whisper::WhisperContext;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+/// Idle time before the model is unloaded, unless configured.
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u32 = 300;
+/// How often the idle check runs.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Payload of the `model-loading` and `model-ready` events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelEvent {
+    pub model_path: String,
+}
+
+struct LoadedModel {
+    path: PathBuf,
+    context: WhisperContext,
+    last_used: Instant,
+}
+
+struct Cache {
+    loaded: Mutex<Option<LoadedModel>>,
+    /// Seconds an unused model stays loaded; `0` keeps it indefinitely.
+    /// Kept outside the mutex, which is held for a whole transcription, so
+    /// changing it never waits.
+    idle_timeout_secs: AtomicU64,
+}
+
+/// Shared handle to the model cache; clones refer to the same model.
+#[derive(Clone)]
+pub struct ModelManager {
+    cache: Arc<Cache>,
+    app: Arc<OnceLock<tauri::AppHandle>>,
+}
+
+impl std::fmt::Debug for ModelManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelManager").finish_non_exhaustive()
+    }
+}
+
+impl ModelManager {
+    /// Create the cache and its idle-unload thread, which exits once every
+    /// handle is dropped. `idle_timeout_secs` of `0` never unloads.
+    pub fn new(idle_timeout_secs: Option<u32>) -> Self {
+        let cache = Arc::new(Cache {
+            loaded: Mutex::new(None),
+            idle_timeout_secs: AtomicU64::new(idle_timeout(idle_timeout_secs)),
+        });
+        let weak = Arc::downgrade(&cache);
+        std::thread::Builder::new()
+            .name("kotoba-model-idle".to_string())
+            .spawn(move || unload_when_idle(weak))
+            .expect("failed to spawn model idle thread");
+        Self {
+            cache,
+            app: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Set the app handle used for loading events; loads before this are
+    /// silent.
+    pub fn attach(&self, app: tauri::AppHandle) {
+        let _ = self.app.set(app);
+    }
+
+    pub fn set_idle_timeout(&self, idle_timeout_secs: Option<u32>) {
+        self.cache
+            .idle_timeout_secs
+            .store(idle_timeout(idle_timeout_secs), Ordering::Relaxed);
+    }
+
+    /// Unload `model_path` if it is the loaded model, waiting for any
+    /// transcription using it to finish.
+    pub fn evict(&self, model_path: &Path) {
+        let mut loaded = self.lock();
+        if loaded.as_ref().is_some_and(|m| m.path == model_path) {
+            *loaded = None;
+        }
+    }
+
+    /// Load `model_path` on a background thread if it is not loaded already.
+    pub fn preload(&self, model_path: PathBuf) {
+        let manager = self.clone();
+        std::thread::Builder::new()
+            .name("kotoba-model-preload".to_string())
+            .spawn(move || {
+                if let Err(e) = manager.with_context(&model_path, |_| ()) {
+                    log::warn!("Failed to preload speech model: {e}");
+                }
+            })
+            .expect("failed to spawn model preload thread");
+    }
+
+    /// Run `f` with the context for `model_path`, loading it first (and
+    /// replacing any other model) when needed. Callers are serialized, since
+    /// inference needs exclusive access to the context.
+    pub fn with_context<R>(
+        &self,
+        model_path: &Path,
+        f: impl FnOnce(&mut WhisperContext) -> R,
+    ) -> Result<R, String> {
+        let mut loaded = self.lock();
+        if loaded.as_ref().is_none_or(|m| m.path != model_path) {
+            // Free the old model before loading the next one.
+            *loaded = None;
+            *loaded = Some(self.load(model_path)?);
+        }
+        let model = loaded.as_mut().expect("model was just loaded");
+        let result = f(&mut model.context);
+        model.last_used = Instant::now();
+        Ok(result)
+    }
+
+    fn load(&self, model_path: &Path) -> Result<LoadedModel, String> {
+        let event = ModelEvent {
+            model_path: model_path.display().to_string(),
+        };
+        self.emit("model-loading", &event);
+        let started = Instant::now();
+        let context = WhisperContext::new(model_path)?;
+        log::info!(
+            "Loaded speech model {} in {:.1}s",
+            model_path.display(),
+            started.elapsed().as_secs_f32()
+        );
+        self.emit("model-ready", &event);
+        Ok(LoadedModel {
+            path: model_path.to_path_buf(),
+            context,
+            last_used: Instant::now(),
+        })
+    }
+
+    fn emit(&self, event: &str, payload: &ModelEvent) {
+        if let Some(app) = self.app.get() {
+            let _ = app.emit(event, payload);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<LoadedModel>> {
+        self.cache.loaded.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn idle_timeout(secs: Option<u32>) -> u64 {
+    secs.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS).into()
+}
+
+fn unload_when_idle(cache: Weak<Cache>) {
+    loop {
+        std::thread::sleep(IDLE_CHECK_INTERVAL);
+        let Some(cache) = cache.upgrade() else {
+            return;
+        };
+        let timeout = match cache.idle_timeout_secs.load(Ordering::Relaxed) {
+            0 => continue,
+            secs => Duration::from_secs(secs),
+        };
+        // A held lock means the model is in use, so it is not idle.
+        let Ok(mut loaded) = cache.loaded.try_lock() else {
+            continue;
+        };
+        if loaded
+            .as_ref()
+            .is_some_and(|model| model.last_used.elapsed() >= timeout)
+        {
+            log::info!("Unloading idle speech model");
+            *loaded = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_timeout_changes_while_a_model_is_in_use() {
+        let manager = ModelManager::new(None);
+        assert_eq!(
+            manager.cache.idle_timeout_secs.load(Ordering::Relaxed),
+            u64::from(DEFAULT_IDLE_TIMEOUT_SECS)
+        );
+
+        // As held by `with_context` for the length of a transcription.
+        let _in_use = manager.lock();
+        manager.set_idle_timeout(Some(0));
+        assert_eq!(manager.cache.idle_timeout_secs.load(Ordering::Relaxed), 0);
+    }
+}
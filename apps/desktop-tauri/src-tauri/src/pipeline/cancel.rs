@@ -0,0 +1,106 @@
+//! Cancellation for a pipeline run, checked by whisper between compute steps
+//! and raced against the formatter request.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Error returned by a cancelled run.
+pub const CANCELLED: &str = "Transcription cancelled";
+
+/// Shared flag; clones observe the same cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// `Err(CANCELLED)` once cancelled, for `?` between stages.
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resolve once the token is cancelled.
+    pub async fn cancelled(&self) {
+        let notified = self.0.notify.notified();
+        tokio::pin!(notified);
+        // Register before checking the flag so a concurrent `cancel` is not
+        // missed.
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn check_fails_once_cancelled() {
+        let token = CancelToken::default();
+        assert_eq!(token.check(), Ok(()));
+        token.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(CANCELLED.to_string()));
+        // Cancelling again changes nothing.
+        token.cancel();
+        assert_eq!(token.check(), Err(CANCELLED.to_string()));
+    }
+
+    #[test]
+    fn clones_share_cancellation() {
+        let token = CancelToken::default();
+        let clone = token.clone();
+        let other = CancelToken::default();
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(!other.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancelled_resolves_when_cancelled_first() {
+        let token = CancelToken::default();
+        token.cancel();
+        tokio::time::timeout(Duration::from_secs(1), token.cancelled())
+            .await
+            .expect("already cancelled");
+    }
+
+    #[tokio::test]
+    async fn cancelled_wakes_a_waiting_task() {
+        let token = CancelToken::default();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        token.cancel();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter woken")
+            .unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Rule-based stand-in for the LLM formatter, used when it is disabled or
+//! fails: filler words are removed and punctuation is restored from segment
+//! timing, for Japanese and English.
+
+use crate::pipeline::normalize::is_japanese;
+use crate::whisper::Segment;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A gap between segments at least this long ends a sentence; shorter ones
+/// get a comma.
+const SENTENCE_PAUSE_MS: i64 = 800;
+
+/// Fillers with any trailing comma. Japanese ones need the drawn-out vowel,
+/// since plain "あの" and "その" are demonstratives, and must start a word;
+/// see [`remove_japanese_fillers`].
+static JAPANESE_FILLERS: OnceLock<Regex> = OnceLock::new();
+static ENGLISH_FILLERS: OnceLock<Regex> = OnceLock::new();
+
+fn japanese_fillers() -> &'static Regex {
+    JAPANESE_FILLERS.get_or_init(|| {
+        Regex::new(
+            "(?:えー+っ?と|ええと|えっと|あの[ー〜ぉ]+|その[ー〜]+|えー+|あー+|うー+ん|んー+)[、,]?",
+        )
+        .unwrap()
+    })
+}
+
+fn english_fillers() -> &'static Regex {
+    ENGLISH_FILLERS.get_or_init(|| Regex::new(r"(?i)\b(?:um+|uh+|erm+|er|hmm+)\b[,.]?\s*").unwrap())
+}
+
+/// Join segment texts without fillers, punctuated by the pauses between them.
+pub fn format(segments: &[Segment]) -> String {
+    let cleaned: Vec<(&Segment, String)> = segments
+        .iter()
+        .map(|s| (s, remove_fillers(&s.text)))
+        .filter(|(_, text)| !text.is_empty())
+        .collect();
+    let japanese = cleaned.iter().any(|(_, t)| t.chars().any(is_japanese));
+    let (comma, period, separator) = if japanese {
+        ("、", "。", "")
+    } else {
+        (",", ".", " ")
+    };
+
+    let mut out = String::new();
+    let mut sentence_start = true;
+    for (i, (segment, text)) in cleaned.iter().enumerate() {
+        if !out.is_empty() {
+            out.push_str(separator);
+        }
+        if sentence_start && !japanese {
+            out.push_str(&capitalize(text));
+        } else {
+            out.push_str(text);
+        }
+
+        let ends_sentence = cleaned
+            .get(i + 1)
+            .is_none_or(|(next, _)| next.start_ms - segment.end_ms >= SENTENCE_PAUSE_MS);
+        let last = text.chars().last().unwrap_or(' ');
+        if is_punctuation(last) {
+            sentence_start = matches!(last, '。' | '.' | '?' | '!' | '？' | '！');
+        } else {
+            out.push_str(if ends_sentence { period } else { comma });
+            sentence_start = ends_sentence;
+        }
+    }
+    out
+}
+
+fn remove_fillers(text: &str) -> String {
+    let text = remove_japanese_fillers(text);
+    let text = english_fillers().replace_all(&text, "");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Remove Japanese fillers at the start of the text or after punctuation,
+/// whitespace or another filler, so words like まあー and じゃあー survive.
+fn remove_japanese_fillers(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut kept = 0;
+    for m in japanese_fillers().find_iter(text) {
+        out.push_str(&text[kept..m.start()]);
+        kept = m.start();
+        let after_boundary = out
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || is_punctuation(c));
+        if after_boundary {
+            kept = m.end();
+        }
+    }
+    out.push_str(&text[kept..]);
+    out
+}
+
+fn is_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '。' | '、' | '.' | ',' | '?' | '!' | '？' | '！' | '…' | '」' | '』' | ')' | '）'
+    )
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: i64, end_ms: i64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: 0.0,
+            tokens: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn removes_fillers() {
+        let cases = [
+            ("えー今日は", "今日は"),
+            ("えーっと、それは", "それは"),
+            ("あのー、明日", "明日"),
+            ("えっと、それは", "それは"),
+            ("um I think so", "I think so"),
+            ("Uh, yes", "yes"),
+            ("あの人はその本を読んだ", "あの人はその本を読んだ"),
+            ("えー、meeting は um 三時", "meeting は 三時"),
+            ("umbrella", "umbrella"),
+            ("えー、あのー、はい", "はい"),
+            ("まあーいいか", "まあーいいか"),
+            ("じゃあー行こう", "じゃあー行こう"),
+            ("そうだなあー。えー次", "そうだなあー。次"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(remove_fillers(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn punctuates_by_pause() {
+        let short = SENTENCE_PAUSE_MS - 1;
+        let long = SENTENCE_PAUSE_MS;
+        let cases = [
+            (vec!["今日は", "晴れです"], short, "今日は、晴れです。"),
+            (vec!["今日は", "晴れです"], long, "今日は。晴れです。"),
+            (vec!["本当？", "はい"], long, "本当？はい。"),
+            (vec!["そうですね、", "はい"], long, "そうですね、はい。"),
+            (
+                vec!["hello there", "how are you?"],
+                short,
+                "Hello there, how are you?",
+            ),
+            (
+                vec!["hello there", "how are you?"],
+                long,
+                "Hello there. How are you?",
+            ),
+            (vec!["really?", "yes"], short, "Really? Yes."),
+            (vec!["um", "okay"], long, "Okay."),
+            (
+                vec!["えー、今日は", "meeting です"],
+                short,
+                "今日は、meeting です。",
+            ),
+            (vec![], long, ""),
+        ];
+        for (texts, gap, expected) in cases {
+            let segments: Vec<Segment> = texts
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let start = i as i64 * (1000 + gap);
+                    segment(start, start + 1000, text)
+                })
+                .collect();
+            assert_eq!(format(&segments), expected, "{texts:?} with a {gap}ms gap");
+        }
+    }
+}
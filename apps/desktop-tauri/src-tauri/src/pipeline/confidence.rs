@@ -0,0 +1,246 @@
+//! Confidence scoring from whisper token probabilities.
+
+use crate::whisper::Segment;
+use serde::Serialize;
+
+/// Tokens decoded with a lower probability are flagged for review.
+const LOW_CONFIDENCE_THRESHOLD: f32 = 0.5;
+
+/// A run of low-probability tokens in the whisper transcript.
+///
+/// Offsets count characters (Unicode scalar values): in the raw whisper
+/// transcript as returned by [`assess`], and in `Transcription.text` once
+/// [`relocate`] has run.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LowConfidenceSpan {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// Lowest token probability in the span.
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Confidence {
+    /// Mean token probability in `0.0..=1.0`; `None` without any tokens.
+    pub score: Option<f64>,
+    pub low_confidence_spans: Vec<LowConfidenceSpan>,
+}
+
+/// Score the transcript formed by concatenating `segments` and trimming it,
+/// as the pipeline does.
+pub fn assess(segments: &[Segment]) -> Confidence {
+    let probs: Vec<f32> = segments
+        .iter()
+        .flat_map(|s| s.tokens.iter().map(|t| t.p))
+        .collect();
+    let score = (!probs.is_empty())
+        .then(|| probs.iter().map(|&p| p as f64).sum::<f64>() / probs.len() as f64);
+
+    Confidence {
+        score,
+        low_confidence_spans: low_confidence_spans(segments),
+    }
+}
+
+/// Find `spans` in `text`, the transcript after post-processing, in order.
+/// Spans whose text a later stage rewrote cannot be placed and are dropped.
+pub fn relocate(spans: Vec<LowConfidenceSpan>, text: &str) -> Vec<LowConfidenceSpan> {
+    let mut from = 0;
+    spans
+        .into_iter()
+        .filter_map(|span| {
+            let found = from + text[from..].find(&span.text)?;
+            from = found + span.text.len();
+            let start = text[..found].chars().count();
+            Some(LowConfidenceSpan {
+                start,
+                end: start + span.text.chars().count(),
+                ..span
+            })
+        })
+        .collect()
+}
+
+fn low_confidence_spans(segments: &[Segment]) -> Vec<LowConfidenceSpan> {
+    let full: String = segments.iter().map(|s| s.text.as_str()).collect();
+    let lead = full.len() - full.trim_start().len();
+    let text_end = full.trim_end().len();
+
+    // Byte ranges of low-probability tokens in `full`, adjacent ones merged.
+    let mut ranges: Vec<(usize, usize, f32)> = Vec::new();
+    let mut segment_start = 0;
+    for segment in segments {
+        let token_bytes: usize = segment.tokens.iter().map(|t| t.len).sum();
+        // Token texts only line up with the segment text if nothing was lost
+        // to invalid UTF-8.
+        if token_bytes == segment.text.len() {
+            let mut pos = segment_start;
+            for token in &segment.tokens {
+                let (start, end) = (pos, pos + token.len);
+                pos = end;
+                if token.p >= LOW_CONFIDENCE_THRESHOLD {
+                    continue;
+                }
+                match ranges.last_mut() {
+                    Some(last) if last.1 == start => {
+                        last.1 = end;
+                        last.2 = last.2.min(token.p);
+                    }
+                    _ => ranges.push((start, end, token.p)),
+                }
+            }
+        }
+        segment_start += segment.text.len();
+    }
+
+    ranges
+        .into_iter()
+        .filter_map(|(start, end, probability)| {
+            // Widen to whole characters, then drop the word-separating space
+            // whisper puts at the front of tokens.
+            let start = floor_char_boundary(&full, start.max(lead));
+            let end = ceil_char_boundary(&full, end.min(text_end));
+            let text = full.get(start..end)?.trim();
+            if text.is_empty() {
+                return None;
+            }
+            let start = start + (full[start..end].len() - full[start..end].trim_start().len());
+            let char_start = full[lead..start].chars().count();
+            Some(LowConfidenceSpan {
+                start: char_start,
+                end: char_start + text.chars().count(),
+                text: text.to_string(),
+                probability,
+            })
+        })
+        .collect()
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::Token;
+
+    /// A segment whose tokens are `(text, p)` pairs.
+    fn segment(tokens: &[(&str, f32)]) -> Segment {
+        let text: String = tokens.iter().map(|(t, _)| *t).collect();
+        let lens: Vec<(usize, f32)> = tokens.iter().map(|(t, p)| (t.len(), *p)).collect();
+        raw_segment(&text, &lens)
+    }
+
+    /// A segment with tokens given as `(byte length, p)`, which may split
+    /// characters or disagree with `text`.
+    fn raw_segment(text: &str, tokens: &[(usize, f32)]) -> Segment {
+        Segment {
+            start_ms: 0,
+            end_ms: 1000,
+            text: text.to_string(),
+            no_speech_prob: 0.0,
+            avg_logprob: 0.0,
+            tokens: tokens.iter().map(|&(len, p)| Token { len, p }).collect(),
+        }
+    }
+
+    fn span(start: usize, end: usize, text: &str) -> LowConfidenceSpan {
+        LowConfidenceSpan {
+            start,
+            end,
+            text: text.to_string(),
+            probability: 0.3,
+        }
+    }
+
+    #[test]
+    fn relocates_spans_onto_the_final_text() {
+        // Raw "えーと今日は晴れです" after filler removal and punctuation.
+        let spans = vec![span(3, 5, "今日"), span(6, 8, "晴れ"), span(0, 3, "えーと")];
+        assert_eq!(
+            relocate(spans, "今日は晴れです。"),
+            [span(0, 2, "今日"), span(3, 5, "晴れ")]
+        );
+
+        // Repeated text is matched in order, not at its first occurrence.
+        let spans = vec![span(0, 2, "はい"), span(3, 5, "はい")];
+        assert_eq!(
+            relocate(spans, "はい、はい。"),
+            [span(0, 2, "はい"), span(3, 5, "はい")]
+        );
+    }
+
+    /// Expected `(start, end, text, probability)`.
+    type Span = (usize, usize, &'static str, f32);
+    type Case = (&'static str, Vec<Segment>, Vec<Span>, Option<f64>);
+
+    #[test]
+    fn spans_and_scores() {
+        let cases: Vec<Case> = vec![
+            (
+                "multibyte Japanese",
+                vec![segment(&[("今日は", 0.9), ("晴れ", 0.2), ("です", 0.9)])],
+                vec![(3, 5, "晴れ", 0.2)],
+                Some(2.0 / 3.0),
+            ),
+            (
+                "token splitting a character",
+                vec![raw_segment("日本語", &[(4, 0.9), (2, 0.2), (3, 0.9)])],
+                vec![(1, 2, "本", 0.2)],
+                Some(2.0 / 3.0),
+            ),
+            (
+                "leading space",
+                vec![segment(&[(" Um", 0.2), (" hello", 0.9), (" world", 0.3)])],
+                vec![(0, 2, "Um", 0.2), (9, 14, "world", 0.3)],
+                Some(1.4 / 3.0),
+            ),
+            (
+                "neighbours merged across segments",
+                vec![
+                    segment(&[(" The", 0.9), (" cat", 0.3)]),
+                    segment(&[(" sat", 0.4), (" down", 0.9)]),
+                ],
+                vec![(4, 11, "cat sat", 0.3)],
+                Some(0.625),
+            ),
+            (
+                "mismatched token lengths",
+                vec![raw_segment("Hi", &[(1, 0.1)]), segment(&[(" there", 0.2)])],
+                vec![(3, 8, "there", 0.2)],
+                Some(0.15),
+            ),
+            ("no tokens", vec![raw_segment(" hello", &[])], vec![], None),
+        ];
+        for (name, segments, spans, score) in cases {
+            let confidence = assess(&segments);
+            let expected: Vec<LowConfidenceSpan> = spans
+                .into_iter()
+                .map(|(start, end, text, probability)| LowConfidenceSpan {
+                    start,
+                    end,
+                    text: text.to_string(),
+                    probability,
+                })
+                .collect();
+            assert_eq!(confidence.low_confidence_spans, expected, "{name}");
+            match (confidence.score, score) {
+                (Some(actual), Some(score)) => assert!((actual - score).abs() < 1e-6, "{name}"),
+                (actual, score) => assert_eq!(actual, score, "{name}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::pipeline::cancel::CancelToken;
+use crate::state::FormatterConfig;
+
+/// Apply the configured formatter to `raw_text`.
+/// Returns the final text and the formatting model used, if any; on failure
+/// the raw text is kept. Cancelling abandons the request and keeps the raw
+/// text too.
+pub async fn apply(
+    formatter_config: Option<&FormatterConfig>,
+    ollama_url: Option<&str>,
+    raw_text: &str,
+    cancel: &CancelToken,
+) -> (String, Option<String>) {
+    if raw_text.is_empty() {
+        return (String::new(), None);
+    }
+    let (Some(fc), Some(url)) = (formatter_config, ollama_url) else {
+        return (raw_text.to_string(), None);
+    };
+    let Some(model_id) = fc.model_id.as_deref().filter(|_| fc.enabled) else {
+        return (raw_text.to_string(), None);
+    };
+
+    let result = tokio::select! {
+        result = format_with_ollama(url, model_id, raw_text) => result,
+        _ = cancel.cancelled() => return (raw_text.to_string(), None),
+    };
+    match result {
+        Ok(text) => (text, Some(model_id.to_string())),
+        Err(e) => {
+            log::warn!("Ollama formatting failed, keeping raw text: {e}");
+            (raw_text.to_string(), None)
+        }
+    }
+}
+
+/// Call Ollama to format/clean up the raw transcription text.
+async fn format_with_ollama(
+    ollama_url: &str,
+    model_id: &str,
+    text: &str,
+) -> Result<String, String> {
+    let client = reqwest::Client::new();
+    let prompt = format!(
+        "以下の日本語音声認識テキストを自然な文章に整形してください。\
+         句読点を適切に追加し、不要な言い淀みを除去してください。\
+         テキストのみを返し、説明は不要です。\n\n{text}"
+    );
+    let body = serde_json::json!({
+        "model": model_id,
+        "prompt": prompt,
+        "stream": false
+    });
+    let response = client
+        .post(format!("{}/api/generate", ollama_url.trim_end_matches('/')))
+        .json(&body)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    json.get("response")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "Ollama reply has no response text".to_string())
+}
@@ -0,0 +1,582 @@
+//! Inverse text normalization for Japanese: spoken numbers, dates, times,
+//! percentages and amounts of money in written form.
+//!
+//! Whisper writes numbers the way they are read, in kanji ("三千五百円") or
+//! occasionally kana ("にせんにじゅうよねん"). A number is only rewritten when
+//! a unit or counter follows it, since bare kanji numerals are everywhere in
+//! ordinary words (一緒, 五十嵐, 千葉). Kana numbers are further limited to
+//! kana units, and need a 十/百/千-style multiplier unless the unit is
+//! unambiguous (ねん, ぱーせんと); "ごえん" is more likely ご縁 than 5円.
+
+#[cfg(test)]
+mod tests;
+
+use crate::state::{CurrencyStyle, DateStyle, ItnSettings, PercentStyle, TimeStyle};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Settings with defaults resolved.
+#[derive(Debug, Clone, Copy)]
+pub struct Itn {
+    enabled: bool,
+    digit_grouping: bool,
+    keep_large_units: bool,
+    currency_style: CurrencyStyle,
+    percent_style: PercentStyle,
+    date_style: DateStyle,
+    time_style: TimeStyle,
+}
+
+impl Default for Itn {
+    fn default() -> Self {
+        Self::new(&ItnSettings::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    /// 一, いち
+    Digit(u64),
+    /// Arabic digits directly before a multiplier, as in "3千".
+    Digits(u64),
+    /// 十, 百, 千
+    Multiplier(u64),
+    /// 万, 億, 兆
+    Large(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Kanji,
+    Kana,
+}
+
+/// A parsed run of numeral tokens.
+#[derive(Debug)]
+struct Number {
+    value: u64,
+    /// Read digit by digit ("二〇二四"); printed as is, never grouped.
+    digits: Option<String>,
+    end: usize,
+    script: Script,
+    has_multiplier: bool,
+    /// The run was a lone 一 or 十, which double as ordinary words.
+    lone: Option<char>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Percent,
+    Currency {
+        symbol: &'static str,
+        suffix: &'static str,
+    },
+    /// Written after the number unchanged.
+    Counter(&'static str),
+}
+
+const KANJI_UNITS: &[(&str, Unit)] = &[
+    ("年", Unit::Year),
+    ("ヶ月", Unit::Counter("ヶ月")),
+    ("か月", Unit::Counter("か月")),
+    ("カ月", Unit::Counter("カ月")),
+    ("月", Unit::Month),
+    ("日", Unit::Day),
+    ("時間", Unit::Counter("時間")),
+    ("時", Unit::Hour),
+    ("分", Unit::Minute),
+    ("秒", Unit::Second),
+    ("パーセント", Unit::Percent),
+    ("%", Unit::Percent),
+    ("％", Unit::Percent),
+    (
+        "円",
+        Unit::Currency {
+            symbol: "¥",
+            suffix: "円",
+        },
+    ),
+    (
+        "ドル",
+        Unit::Currency {
+            symbol: "$",
+            suffix: "ドル",
+        },
+    ),
+    (
+        "ユーロ",
+        Unit::Currency {
+            symbol: "€",
+            suffix: "ユーロ",
+        },
+    ),
+    (
+        "ポンド",
+        Unit::Currency {
+            symbol: "£",
+            suffix: "ポンド",
+        },
+    ),
+    ("週間", Unit::Counter("週間")),
+    ("歳", Unit::Counter("歳")),
+    ("人", Unit::Counter("人")),
+    ("名", Unit::Counter("名")),
+    ("個", Unit::Counter("個")),
+    ("件", Unit::Counter("件")),
+    ("回", Unit::Counter("回")),
+    ("台", Unit::Counter("台")),
+    ("枚", Unit::Counter("枚")),
+    ("冊", Unit::Counter("冊")),
+    ("倍", Unit::Counter("倍")),
+    ("割", Unit::Counter("割")),
+    ("点", Unit::Counter("点")),
+    ("階", Unit::Counter("階")),
+    ("位", Unit::Counter("位")),
+    ("キロ", Unit::Counter("キロ")),
+    ("メートル", Unit::Counter("メートル")),
+    ("グラム", Unit::Counter("グラム")),
+    ("ページ", Unit::Counter("ページ")),
+];
+
+const KANA_UNITS: &[(&str, Unit)] = &[
+    ("ねん", Unit::Year),
+    ("がつ", Unit::Month),
+    ("にち", Unit::Day),
+    ("じ", Unit::Hour),
+    ("ふん", Unit::Minute),
+    ("ぷん", Unit::Minute),
+    ("びょう", Unit::Second),
+    ("ぱーせんと", Unit::Percent),
+    (
+        "えん",
+        Unit::Currency {
+            symbol: "¥",
+            suffix: "円",
+        },
+    ),
+];
+
+/// Kana readings, longest first where one is a prefix of another.
+const KANA_NUMERALS: &[(&str, Token)] = &[
+    ("ぜろ", Token::Digit(0)),
+    ("れい", Token::Digit(0)),
+    ("いち", Token::Digit(1)),
+    ("いっ", Token::Digit(1)),
+    ("に", Token::Digit(2)),
+    ("さん", Token::Digit(3)),
+    ("よん", Token::Digit(4)),
+    ("よ", Token::Digit(4)),
+    ("ご", Token::Digit(5)),
+    ("ろく", Token::Digit(6)),
+    ("ろっ", Token::Digit(6)),
+    ("なな", Token::Digit(7)),
+    ("しち", Token::Digit(7)),
+    ("はち", Token::Digit(8)),
+    ("はっ", Token::Digit(8)),
+    ("きゅう", Token::Digit(9)),
+    ("じゅう", Token::Multiplier(10)),
+    ("じゅっ", Token::Multiplier(10)),
+    ("じっ", Token::Multiplier(10)),
+    ("ひゃく", Token::Multiplier(100)),
+    ("びゃく", Token::Multiplier(100)),
+    ("ぴゃく", Token::Multiplier(100)),
+    ("せん", Token::Multiplier(1000)),
+    ("ぜん", Token::Multiplier(1000)),
+    ("まん", Token::Large(10_000)),
+    ("おく", Token::Large(100_000_000)),
+];
+
+/// Words that start with a numeral and a unit but are not numbers.
+const EXCEPTIONS: &[&str] = &[
+    "一人",
+    "二人",
+    "一時的",
+    "一時期",
+    "一時停止",
+    "三日月",
+    "一日中",
+    "一分一秒",
+];
+
+impl Itn {
+    pub fn new(settings: &ItnSettings) -> Self {
+        Self {
+            enabled: settings.enabled.unwrap_or(false),
+            digit_grouping: settings.digit_grouping.unwrap_or(true),
+            keep_large_units: settings.keep_large_units.unwrap_or(true),
+            currency_style: settings.currency_style.unwrap_or(CurrencyStyle::Suffix),
+            percent_style: settings.percent_style.unwrap_or(PercentStyle::Halfwidth),
+            date_style: settings.date_style.unwrap_or(DateStyle::Kanji),
+            time_style: settings.time_style.unwrap_or(TimeStyle::Kanji),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            if let Some(word) = EXCEPTIONS.iter().find(|w| starts_with(&chars, i, w)) {
+                out.push_str(word);
+                i += word.chars().count();
+                continue;
+            }
+            // Decimals and numbers already in digits are left alone.
+            if to_ascii_digit(chars[i]).is_some()
+                && arabic_digits_before_numeral(&chars, i).is_none()
+            {
+                while let Some(&c) = chars.get(i) {
+                    if to_ascii_digit(c).is_none() && !matches!(c, '.' | ',' | '．' | '，') {
+                        break;
+                    }
+                    out.push(c);
+                    i += 1;
+                }
+                continue;
+            }
+            if let Some((written, end)) = self.convert_at(&chars, i, &out) {
+                out.push_str(&written);
+                i = end;
+                continue;
+            }
+            // Skip the rest of a numeral run that was left as is, so that
+            // "五十嵐" is not retried as "十嵐".
+            let end = parse_number(&chars, i).map_or(i + 1, |n| n.end.max(i + 1));
+            out.extend(&chars[i..end]);
+            i = end;
+        }
+        self.restyle_dates_and_times(out)
+    }
+
+    /// Written form of the number and unit starting at `i`, and where they end.
+    fn convert_at(&self, chars: &[char], i: usize, before: &str) -> Option<(String, usize)> {
+        let number = parse_number(chars, i)?;
+        let (decimal, number_end) = match number.script {
+            Script::Kanji => parse_decimal(chars, number.end),
+            Script::Kana => (None, number.end),
+        };
+        let units = match number.script {
+            Script::Kanji => KANJI_UNITS,
+            Script::Kana => KANA_UNITS,
+        };
+        let (unit, unit_len) = match_unit(chars, number_end, units)?;
+        let end = number_end + unit_len;
+
+        if number.script == Script::Kana
+            && !number.has_multiplier
+            && !matches!(unit, Unit::Year | Unit::Percent)
+        {
+            return None;
+        }
+        if let (Some(lone), None) = (number.lone, &decimal) {
+            // 十分 (enough), 一分 (a little), 一時 (for a while) are ordinary
+            // words unless they are clearly part of a time.
+            let after = chars.get(end).copied();
+            let time_context = match unit {
+                Unit::Minute => before.ends_with('時'),
+                Unit::Hour if lone == '一' => {
+                    before.ends_with("午前")
+                        || before.ends_with("午後")
+                        || after == Some('半')
+                        || after.and_then(to_ascii_digit).is_some()
+                        || parse_number(chars, end).is_some()
+                }
+                _ => true,
+            };
+            if !time_context {
+                return None;
+            }
+        }
+
+        let mut written = match &number.digits {
+            Some(digits) => digits.clone(),
+            None => self.format_value(number.value, unit),
+        };
+        if let Some(decimal) = decimal {
+            written.push('.');
+            written.push_str(&decimal);
+        }
+        Some((self.with_unit(written, unit), end))
+    }
+
+    fn format_value(&self, value: u64, unit: Unit) -> String {
+        let is_calendar = matches!(
+            unit,
+            Unit::Year | Unit::Month | Unit::Day | Unit::Hour | Unit::Minute | Unit::Second
+        );
+        if self.keep_large_units && value >= 10_000 && !is_calendar {
+            return with_large_units(value);
+        }
+        if self.digit_grouping && !is_calendar {
+            return group_digits(value);
+        }
+        value.to_string()
+    }
+
+    fn with_unit(&self, number: String, unit: Unit) -> String {
+        match unit {
+            Unit::Year => number + "年",
+            Unit::Month => number + "月",
+            Unit::Day => number + "日",
+            Unit::Hour => number + "時",
+            Unit::Minute => number + "分",
+            Unit::Second => number + "秒",
+            Unit::Percent => {
+                number
+                    + match self.percent_style {
+                        PercentStyle::Halfwidth => "%",
+                        PercentStyle::Fullwidth => "％",
+                        PercentStyle::Katakana => "パーセント",
+                    }
+            }
+            Unit::Currency { symbol, suffix } => match self.currency_style {
+                CurrencyStyle::Suffix => number + suffix,
+                CurrencyStyle::Symbol => format!("{symbol}{number}"),
+            },
+            Unit::Counter(counter) => number + counter,
+        }
+    }
+
+    fn restyle_dates_and_times(&self, text: String) -> String {
+        static DATE: OnceLock<Regex> = OnceLock::new();
+        static TIME: OnceLock<Regex> = OnceLock::new();
+
+        let text = match self.date_style {
+            DateStyle::Kanji => text,
+            DateStyle::Slash => DATE
+                .get_or_init(|| Regex::new(r"(\d{1,4})年(\d{1,2})月(\d{1,2})日").unwrap())
+                .replace_all(&text, "$1/$2/$3")
+                .into_owned(),
+        };
+        match self.time_style {
+            TimeStyle::Kanji => text,
+            TimeStyle::Colon => TIME
+                .get_or_init(|| Regex::new(r"(\d{1,2})時(?:(\d{1,2})分|(半))").unwrap())
+                .replace_all(&text, |caps: &Captures| {
+                    let minutes = match (caps.get(2), caps.get(3)) {
+                        (Some(m), _) => m.as_str().parse::<u32>().unwrap_or(0),
+                        _ => 30,
+                    };
+                    format!("{}:{minutes:02}", &caps[1])
+                })
+                .into_owned(),
+        }
+    }
+}
+
+fn starts_with(chars: &[char], i: usize, word: &str) -> bool {
+    word.chars()
+        .enumerate()
+        .all(|(n, c)| chars.get(i + n) == Some(&c))
+}
+
+fn to_ascii_digit(c: char) -> Option<u64> {
+    match c {
+        '0'..='9' => Some(c as u64 - '0' as u64),
+        '０'..='９' => Some(c as u64 - '０' as u64),
+        _ => None,
+    }
+}
+
+fn kanji_digit(c: char) -> Option<u64> {
+    let digit = match c {
+        '〇' | '零' => 0,
+        '一' => 1,
+        '二' => 2,
+        '三' => 3,
+        '四' => 4,
+        '五' => 5,
+        '六' => 6,
+        '七' => 7,
+        '八' => 8,
+        '九' => 9,
+        _ => return None,
+    };
+    Some(digit)
+}
+
+/// Value and length of Arabic digits at `i` that a 十/百/千/万/億/兆 follows.
+fn arabic_digits_before_numeral(chars: &[char], i: usize) -> Option<(u64, usize)> {
+    let mut value: u64 = 0;
+    let mut len = 0;
+    while let Some(d) = chars.get(i + len).copied().and_then(to_ascii_digit) {
+        value = value.checked_mul(10)?.checked_add(d)?;
+        len += 1;
+    }
+    let next = chars.get(i + len)?;
+    (len > 0 && matches!(next, '十' | '百' | '千' | '万' | '億' | '兆')).then_some((value, len))
+}
+
+fn next_token(chars: &[char], i: usize) -> Option<(Token, usize, Script)> {
+    let c = *chars.get(i)?;
+    if let Some(d) = kanji_digit(c) {
+        return Some((Token::Digit(d), 1, Script::Kanji));
+    }
+    let kanji = match c {
+        '十' => Some(Token::Multiplier(10)),
+        '百' => Some(Token::Multiplier(100)),
+        '千' => Some(Token::Multiplier(1000)),
+        '万' => Some(Token::Large(10_000)),
+        '億' => Some(Token::Large(100_000_000)),
+        '兆' => Some(Token::Large(1_000_000_000_000)),
+        _ => None,
+    };
+    if let Some(token) = kanji {
+        return Some((token, 1, Script::Kanji));
+    }
+    if let Some((value, len)) = arabic_digits_before_numeral(chars, i) {
+        return Some((Token::Digits(value), len, Script::Kanji));
+    }
+    let (token, len) = KANA_NUMERALS
+        .iter()
+        .find(|(reading, _)| starts_with(chars, i, reading))
+        .map(|(reading, token)| (*token, reading.chars().count()))?;
+    // A kana unit at least as long ends the number: "にち" after "に" is the
+    // day, while "じゅう" is ten rather than the hour.
+    match match_unit(chars, i, KANA_UNITS) {
+        Some((_, unit_len)) if unit_len >= len => None,
+        _ => Some((token, len, Script::Kana)),
+    }
+}
+
+/// Read the longest valid number at `start`. Tokens that would make the value
+/// ill-formed ("十百", "万億") end the number instead.
+fn parse_number(chars: &[char], start: usize) -> Option<Number> {
+    let mut total: u64 = 0;
+    let mut section: u64 = 0;
+    let mut current: Option<u64> = None;
+    let mut digits: Option<String> = None;
+    let mut last_multiplier = u64::MAX;
+    let mut last_large = u64::MAX;
+    let mut has_multiplier = false;
+    let mut script = None;
+    let mut count = 0;
+    let mut end = start;
+
+    while let Some((token, len, token_script)) = next_token(chars, end) {
+        if script.is_some_and(|s| s != token_script) {
+            break;
+        }
+        match token {
+            Token::Digit(d) => match current {
+                None => current = Some(d),
+                // 二〇二四: digit by digit, kanji only.
+                Some(value) if token_script == Script::Kanji && section == 0 && total == 0 => {
+                    let seq = digits.get_or_insert_with(|| value.to_string());
+                    seq.push(char::from_digit(d as u32, 10)?);
+                    current = Some(value.checked_mul(10)?.checked_add(d)?);
+                }
+                Some(_) => break,
+            },
+            Token::Digits(v) => {
+                if current.is_some() {
+                    break;
+                }
+                current = Some(v);
+            }
+            Token::Multiplier(m) => {
+                if digits.is_some() || m >= last_multiplier {
+                    break;
+                }
+                section += current.take().unwrap_or(1).checked_mul(m)?;
+                last_multiplier = m;
+                has_multiplier = true;
+            }
+            Token::Large(l) => {
+                let coefficient = section + current.unwrap_or(0);
+                if digits.is_some() || coefficient == 0 || l >= last_large {
+                    break;
+                }
+                total = total.checked_add(coefficient.checked_mul(l)?)?;
+                section = 0;
+                current = None;
+                last_multiplier = u64::MAX;
+                last_large = l;
+                has_multiplier = true;
+            }
+        }
+        script = Some(token_script);
+        count += 1;
+        end += len;
+    }
+
+    let script = script?;
+    Some(Number {
+        value: total + section + current.unwrap_or(0),
+        digits,
+        end,
+        script,
+        has_multiplier,
+        lone: Some(chars[start]).filter(|c| count == 1 && matches!(c, '一' | '十')),
+    })
+}
+
+/// Kanji digits after 点, as in "三点五パーセント". Returns the decimal digits
+/// and where the number ends.
+fn parse_decimal(chars: &[char], end: usize) -> (Option<String>, usize) {
+    if chars.get(end) != Some(&'点') {
+        return (None, end);
+    }
+    let decimal: String = chars[end + 1..]
+        .iter()
+        .map_while(|&c| kanji_digit(c))
+        .map(|d| char::from_digit(d as u32, 10).unwrap_or('0'))
+        .collect();
+    let decimal_end = end + 1 + decimal.chars().count();
+    if decimal.is_empty() || match_unit(chars, decimal_end, KANJI_UNITS).is_none() {
+        return (None, end);
+    }
+    (Some(decimal), decimal_end)
+}
+
+fn match_unit(chars: &[char], i: usize, units: &[(&str, Unit)]) -> Option<(Unit, usize)> {
+    units
+        .iter()
+        .filter(|(surface, _)| starts_with(chars, i, surface))
+        .max_by_key(|(surface, _)| surface.chars().count())
+        .map(|(surface, unit)| (*unit, surface.chars().count()))
+}
+
+/// "3,500"
+fn group_digits(value: u64) -> String {
+    let digits = value.to_string();
+    let groups: Vec<&str> = digits
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect();
+    groups.join(",")
+}
+
+/// "3億5000万", "1万2000"
+fn with_large_units(value: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [
+        (1_000_000_000_000, "兆"),
+        (100_000_000, "億"),
+        (10_000, "万"),
+    ];
+    let mut out = String::new();
+    let mut rest = value;
+    for (size, name) in UNITS {
+        let count = rest / size;
+        if count > 0 {
+            out.push_str(&count.to_string());
+            out.push_str(name);
+        }
+        rest %= size;
+    }
+    if rest > 0 {
+        out.push_str(&rest.to_string());
+    }
+    out
+}
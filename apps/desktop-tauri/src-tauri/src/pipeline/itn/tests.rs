@@ -0,0 +1,359 @@
+use super::*;
+
+/// Run every case and report all mismatches at once.
+fn check(itn: &Itn, cases: &[(&str, &str)]) {
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|(input, expected)| {
+            let actual = itn.apply(input);
+            (actual != *expected).then(|| format!("{input} → {actual} (expected {expected})"))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+/// `settings`, enabled unless they say otherwise.
+fn with(mut settings: ItnSettings) -> Itn {
+    settings.enabled.get_or_insert(true);
+    Itn::new(&settings)
+}
+
+fn enabled() -> Itn {
+    with(ItnSettings::default())
+}
+
+#[test]
+fn amounts_of_money() {
+    check(
+        &enabled(),
+        &[
+            ("三千五百円", "3,500円"),
+            ("十円", "10円"),
+            ("二十円", "20円"),
+            ("百円", "100円"),
+            ("千円", "1,000円"),
+            ("九百九十九円", "999円"),
+            ("一万円", "1万円"),
+            ("十万円", "10万円"),
+            ("百万円", "100万円"),
+            ("三百万円", "300万円"),
+            ("一千万円", "1000万円"),
+            ("一万二千円", "1万2000円"),
+            ("二十三万四千五百六十七円", "23万4567円"),
+            ("三億円", "3億円"),
+            ("三億五千万円", "3億5000万円"),
+            ("一兆円", "1兆円"),
+            ("五ドル", "5ドル"),
+            ("千二百ユーロ", "1,200ユーロ"),
+            ("八百ポンド", "800ポンド"),
+            ("3千5百円", "3,500円"),
+            ("5万円", "5万円"),
+            ("さんぜんえん", "3,000円"),
+            ("ひゃくえん", "100円"),
+            ("ろっぴゃくえん", "600円"),
+            ("はっせんえん", "8,000円"),
+        ],
+    );
+}
+
+#[test]
+fn dates() {
+    check(
+        &enabled(),
+        &[
+            ("二〇二四年", "2024年"),
+            ("一九九五年", "1995年"),
+            ("二千二十四年", "2024年"),
+            ("にせんにじゅうよねん", "2024年"),
+            ("せんきゅうひゃくきゅうじゅうごねん", "1995年"),
+            ("令和六年", "令和6年"),
+            ("三月五日", "3月5日"),
+            ("四月一日", "4月1日"),
+            ("十二月三十一日", "12月31日"),
+            ("じゅうにがつ", "12月"),
+            ("二〇二四年三月五日", "2024年3月5日"),
+            ("一月", "1月"),
+            ("十日", "10日"),
+        ],
+    );
+}
+
+#[test]
+fn times() {
+    check(
+        &enabled(),
+        &[
+            ("三時", "3時"),
+            ("十時", "10時"),
+            ("二十三時", "23時"),
+            ("午前一時", "午前1時"),
+            ("午後三時半", "午後3時半"),
+            ("一時半", "1時半"),
+            ("三時十五分", "3時15分"),
+            ("三時十分", "3時10分"),
+            ("一時十分", "1時10分"),
+            ("四十分", "40分"),
+            ("十秒", "10秒"),
+            ("一時間", "1時間"),
+            ("二時間", "2時間"),
+            ("にじゅうじ", "20時"),
+            ("じゅうじ", "10時"),
+            ("じっぷん", "10分"),
+            ("さんじゅっぷん", "30分"),
+        ],
+    );
+}
+
+#[test]
+fn percentages() {
+    check(
+        &enabled(),
+        &[
+            ("五十パーセント", "50%"),
+            ("百パーセント", "100%"),
+            ("十％", "10%"),
+            ("三点五パーセント", "3.5%"),
+            ("ごじゅうぱーせんと", "50%"),
+            ("ごぱーせんと", "5%"),
+        ],
+    );
+}
+
+#[test]
+fn counters() {
+    check(
+        &enabled(),
+        &[
+            ("三人", "3人"),
+            ("三千人", "3,000人"),
+            ("二十歳", "20歳"),
+            ("五回", "5回"),
+            ("一回", "1回"),
+            ("三か月", "3か月"),
+            ("六ヶ月", "6ヶ月"),
+            ("二週間", "2週間"),
+            ("十ページ", "10ページ"),
+            ("二倍", "2倍"),
+            ("五点", "5点"),
+            ("三階", "3階"),
+        ],
+    );
+}
+
+#[test]
+fn sentences() {
+    check(
+        &enabled(),
+        &[
+            ("会費は三千五百円です。", "会費は3,500円です。"),
+            (
+                "二〇二四年三月五日の午後三時に集合",
+                "2024年3月5日の午後3時に集合",
+            ),
+            (
+                "売上は前年比百二十パーセントでした",
+                "売上は前年比120%でした",
+            ),
+            (
+                "参加者は三十人、会費は五千円",
+                "参加者は30人、会費は5,000円",
+            ),
+            ("第一回の会議は十時から", "第1回の会議は10時から"),
+        ],
+    );
+}
+
+#[test]
+fn leaves_ordinary_words_alone() {
+    check(
+        &enabled(),
+        &[
+            ("一緒に行く", "一緒に行く"),
+            ("五十嵐さん", "五十嵐さん"),
+            ("千葉県", "千葉県"),
+            ("十分です", "十分です"),
+            ("一分の隙もない", "一分の隙もない"),
+            ("一時的な問題", "一時的な問題"),
+            ("一時は", "一時は"),
+            ("一人で", "一人で"),
+            ("二人とも", "二人とも"),
+            ("三日月", "三日月"),
+            ("一日中", "一日中"),
+            ("万一の場合", "万一の場合"),
+            ("統一", "統一"),
+            ("唯一", "唯一"),
+            ("一番", "一番"),
+            ("もう一度", "もう一度"),
+            ("八百屋", "八百屋"),
+            ("四国と九州", "四国と九州"),
+            ("三千五百", "三千五百"),
+            ("日本にいます", "日本にいます"),
+            ("にじがでた", "にじがでた"),
+            ("いちにち", "いちにち"),
+            ("ごえんがある", "ごえんがある"),
+            ("ざんねん", "ざんねん"),
+        ],
+    );
+}
+
+#[test]
+fn leaves_digits_alone() {
+    check(
+        &enabled(),
+        &[
+            ("2024年", "2024年"),
+            ("12000円", "12000円"),
+            ("1,000円", "1,000円"),
+            ("3.5%", "3.5%"),
+            ("1.5万円", "1.5万円"),
+            ("version 2", "version 2"),
+        ],
+    );
+}
+
+#[test]
+fn without_digit_grouping() {
+    let itn = with(ItnSettings {
+        digit_grouping: Some(false),
+        ..Default::default()
+    });
+    check(
+        &itn,
+        &[
+            ("三千五百円", "3500円"),
+            ("三千人", "3000人"),
+            ("一万二千円", "1万2000円"),
+        ],
+    );
+}
+
+#[test]
+fn without_large_units() {
+    let itn = with(ItnSettings {
+        keep_large_units: Some(false),
+        ..Default::default()
+    });
+    check(
+        &itn,
+        &[
+            ("一万二千円", "12,000円"),
+            ("三億円", "300,000,000円"),
+            ("十万人", "100,000人"),
+            ("三千五百円", "3,500円"),
+        ],
+    );
+
+    let itn = with(ItnSettings {
+        keep_large_units: Some(false),
+        digit_grouping: Some(false),
+        ..Default::default()
+    });
+    check(&itn, &[("一万二千円", "12000円")]);
+}
+
+#[test]
+fn currency_symbols() {
+    let itn = with(ItnSettings {
+        currency_style: Some(CurrencyStyle::Symbol),
+        ..Default::default()
+    });
+    check(
+        &itn,
+        &[
+            ("三千五百円", "¥3,500"),
+            ("さんぜんえん", "¥3,000"),
+            ("五ドル", "$5"),
+            ("千二百ユーロ", "€1,200"),
+            ("八百ポンド", "£800"),
+            ("三億円", "¥3億"),
+        ],
+    );
+}
+
+#[test]
+fn percent_styles() {
+    let cases = [
+        (PercentStyle::Halfwidth, "50%"),
+        (PercentStyle::Fullwidth, "50％"),
+        (PercentStyle::Katakana, "50パーセント"),
+    ];
+    for (style, expected) in cases {
+        let itn = with(ItnSettings {
+            percent_style: Some(style),
+            ..Default::default()
+        });
+        check(&itn, &[("五十パーセント", expected), ("五十％", expected)]);
+    }
+}
+
+#[test]
+fn slash_dates() {
+    let itn = with(ItnSettings {
+        date_style: Some(DateStyle::Slash),
+        ..Default::default()
+    });
+    check(
+        &itn,
+        &[
+            ("二〇二四年三月五日", "2024/3/5"),
+            ("二〇二四年三月五日の午後", "2024/3/5の午後"),
+            ("三月五日", "3月5日"),
+            ("二〇二四年", "2024年"),
+        ],
+    );
+}
+
+#[test]
+fn colon_times() {
+    let itn = with(ItnSettings {
+        time_style: Some(TimeStyle::Colon),
+        ..Default::default()
+    });
+    check(
+        &itn,
+        &[
+            ("午後三時半", "午後3:30"),
+            ("三時十五分", "3:15"),
+            ("三時五分", "3:05"),
+            ("二十三時五十九分", "23:59"),
+            ("三時", "3時"),
+            ("二時間半", "2時間半"),
+        ],
+    );
+}
+
+#[test]
+fn disabled() {
+    let itn = with(ItnSettings {
+        enabled: Some(false),
+        ..Default::default()
+    });
+    check(&itn, &[("三千五百円", "三千五百円")]);
+    // Off unless enabled.
+    check(
+        &Itn::default(),
+        &[("三千五百円", "三千五百円"), ("一日", "一日")],
+    );
+}
+
+#[test]
+fn formatting_helpers() {
+    let grouped = [
+        (0, "0"),
+        (999, "999"),
+        (1000, "1,000"),
+        (1234567, "1,234,567"),
+    ];
+    for (value, expected) in grouped {
+        assert_eq!(group_digits(value), expected);
+    }
+    let units = [
+        (10_000, "1万"),
+        (12_000, "1万2000"),
+        (350_000_000, "3億5000万"),
+        (1_000_000_000_001, "1兆1"),
+    ];
+    for (value, expected) in units {
+        assert_eq!(with_large_units(value), expected);
+    }
+}
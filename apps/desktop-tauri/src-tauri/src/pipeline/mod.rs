@@ -0,0 +1,336 @@
+//! Post-recording processing shared by dictation sessions and file imports:
+//! decode → transcribe → vocabulary → format → ITN → normalize → text rules.
+//! Without a working LLM formatter, the format step falls back to local
+//! filler removal and punctuation.
+
+pub mod cancel;
+pub mod cleanup;
+pub mod confidence;
+pub mod formatting;
+pub mod itn;
+pub mod normalize;
+pub mod prompt;
+pub mod rules;
+pub mod segment_filter;
+pub mod streaming;
+pub mod vocabulary;
+pub mod worker;
+
+use crate::audio;
+use crate::model_manager::ModelManager;
+use crate::pipeline::cancel::CancelToken;
+use crate::pipeline::confidence::{Confidence, LowConfidenceSpan};
+use crate::pipeline::itn::Itn;
+use crate::pipeline::normalize::Normalizer;
+use crate::pipeline::prompt::PromptContext;
+use crate::pipeline::rules::{FiredRule, RuleSet};
+use crate::pipeline::segment_filter::FilteredSegment;
+use crate::pipeline::vocabulary::Vocabulary;
+use crate::pipeline::worker::TranscriptionWorker;
+use crate::state::{AppState, FormatterConfig};
+use crate::vad::{self, SpeechSegment};
+use crate::whisper::{Segment, TranscribeOptions, WHISPER_SAMPLE_RATE};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Settings snapshot taken under the state lock so the pipeline itself runs
+/// without holding it.
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    /// `None` lets whisper auto-detect the language.
+    pub language: Option<String>,
+    /// Languages auto-detection may pick; empty allows all.
+    pub allowed_languages: Vec<String>,
+    pub formatter_config: Option<FormatterConfig>,
+    pub ollama_url: Option<String>,
+    pub model_path: Option<String>,
+    pub worker: TranscriptionWorker,
+    /// Rate of recorded audio, from `RecordingSettings.sample_rate`.
+    pub sample_rate: u32,
+    pub prompt: PromptContext,
+    pub vocabulary: Vocabulary,
+    pub itn: Itn,
+    pub normalizer: Normalizer,
+    pub rules: RuleSet,
+}
+
+impl PipelineConfig {
+    pub fn from_state(state: &AppState) -> Result<Self, String> {
+        let model_path = state.speech_model_path()?;
+        let settings = state.settings()?;
+        let db = state.db()?;
+        let language = settings.dictation.as_ref().and_then(|d| {
+            if d.auto_detect_enabled {
+                None
+            } else {
+                Some(d.selected_language.clone())
+            }
+        });
+        let allowed_languages = settings
+            .dictation
+            .as_ref()
+            .and_then(|d| d.auto_detect_languages.clone())
+            .unwrap_or_default();
+        let ollama_url = settings
+            .model_providers_config
+            .as_ref()
+            .and_then(|c| c.ollama.as_ref())
+            .map(|o| o.url.clone());
+        let vocabulary = Vocabulary::new(db.get_vocabulary().map_err(|e| e.to_string())?);
+        let sample_rate = settings
+            .recording
+            .as_ref()
+            .and_then(|r| r.sample_rate)
+            .unwrap_or(WHISPER_SAMPLE_RATE);
+
+        Ok(Self {
+            language,
+            allowed_languages,
+            formatter_config: settings.formatter_config.clone(),
+            ollama_url,
+            model_path,
+            worker: state.worker.clone(),
+            sample_rate,
+            prompt: PromptContext::load(&db, &settings, &vocabulary)?,
+            vocabulary,
+            itn: settings
+                .inverse_text_normalization
+                .as_ref()
+                .map(Itn::new)
+                .unwrap_or_default(),
+            normalizer: settings
+                .text_normalization
+                .as_ref()
+                .map(Normalizer::new)
+                .unwrap_or_default(),
+            rules: RuleSet::new(db.get_text_rules().map_err(|e| e.to_string())?),
+        })
+    }
+}
+
+/// Pipeline stage, reported to progress callbacks before it starts and, for
+/// transcription, with the percentage done as it runs.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineStage {
+    Decoding,
+    Transcribing,
+    Formatting,
+}
+
+/// Result of running an audio file through the pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOutput {
+    pub text: String,
+    /// Audio length in whole seconds, matching `transcriptions.duration`.
+    pub duration_secs: i64,
+    pub formatting_model: Option<String>,
+    /// Configured or detected language; `None` when nothing was transcribed.
+    pub language: Option<String>,
+    /// Detection probability, `None` when the language was configured.
+    pub language_probability: Option<f32>,
+    /// Whisper segments behind the text, timed from the start of the audio.
+    pub segments: Vec<Segment>,
+    /// Mean token probability of the whisper transcript.
+    pub confidence: Option<f64>,
+    /// Low-confidence spans found in `text`.
+    pub low_confidence_spans: Vec<LowConfidenceSpan>,
+    /// Where the VAD heard speech, relative to the start of the audio.
+    pub speech_segments: Vec<SpeechSegment>,
+    /// Segments dropped as likely hallucinations.
+    pub filtered_segments: Vec<FilteredSegment>,
+    /// Text rules that changed the formatted text.
+    pub fired_rules: Vec<FiredRule>,
+}
+
+/// Whisper output for one recording, before formatting.
+#[derive(Default)]
+struct Transcript {
+    text: String,
+    language: Option<String>,
+    language_probability: Option<f32>,
+    segments: Vec<Segment>,
+    confidence: Confidence,
+    speech_segments: Vec<SpeechSegment>,
+    filtered_segments: Vec<FilteredSegment>,
+}
+
+/// Decode, transcribe and format an audio file. Once `cancel` fires, the run
+/// stops at the next opportunity with [`cancel::CANCELLED`].
+pub async fn process_file(
+    config: &PipelineConfig,
+    audio_path: &str,
+    cancel: &CancelToken,
+    on_progress: impl Fn(PipelineStage, Option<u8>),
+) -> Result<PipelineOutput, String> {
+    on_progress(PipelineStage::Decoding, None);
+    // Straight to the whisper rate: resampling twice costs time and quality.
+    let samples = decode(audio_path, WHISPER_SAMPLE_RATE).await?;
+    cancel.check()?;
+    process_samples(config, samples, WHISPER_SAMPLE_RATE, cancel, on_progress).await
+}
+
+/// Transcribe and format mono samples already decoded at `sample_rate`.
+pub async fn process_samples(
+    config: &PipelineConfig,
+    samples: Vec<f32>,
+    sample_rate: u32,
+    cancel: &CancelToken,
+    on_progress: impl Fn(PipelineStage, Option<u8>),
+) -> Result<PipelineOutput, String> {
+    let duration_secs = samples.len() as i64 / sample_rate.max(1) as i64;
+
+    on_progress(PipelineStage::Transcribing, None);
+    let transcript = transcribe(config, samples, sample_rate, cancel, |percent| {
+        on_progress(PipelineStage::Transcribing, Some(percent))
+    })
+    .await?;
+    let text = config.vocabulary.apply(&transcript.text);
+
+    on_progress(PipelineStage::Formatting, None);
+    let (text, formatting_model) = formatting::apply(
+        config.formatter_config.as_ref(),
+        config.ollama_url.as_deref(),
+        &text,
+        cancel,
+    )
+    .await;
+    cancel.check()?;
+    let text = match formatting_model {
+        Some(_) => text,
+        None => config
+            .vocabulary
+            .apply(&cleanup::format(&transcript.segments)),
+    };
+    let text = config.itn.apply(&text);
+    let text = config.normalizer.apply(&text);
+    let (text, fired_rules) = config.rules.apply(&text);
+    let low_confidence_spans =
+        confidence::relocate(transcript.confidence.low_confidence_spans, &text);
+
+    Ok(PipelineOutput {
+        text,
+        duration_secs,
+        formatting_model,
+        language: transcript.language,
+        language_probability: transcript.language_probability,
+        segments: transcript.segments,
+        confidence: transcript.confidence.score,
+        low_confidence_spans,
+        speech_segments: transcript.speech_segments,
+        filtered_segments: transcript.filtered_segments,
+        fired_rules,
+    })
+}
+
+/// Decode any supported file to mono f32 at `sample_rate`.
+async fn decode(audio_path: &str, sample_rate: u32) -> Result<Vec<f32>, String> {
+    let audio_path = PathBuf::from(audio_path);
+    tauri::async_runtime::spawn_blocking(move || audio::load_mono(&audio_path, sample_rate))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Run whisper over mono samples at `sample_rate`, with leading and trailing
+/// silence trimmed by the VAD. Audio with no detected speech never reaches
+/// whisper, which would otherwise hallucinate on it.
+/// Inference is CPU-bound, so it runs on the transcription worker.
+async fn transcribe(
+    config: &PipelineConfig,
+    samples: Vec<f32>,
+    sample_rate: u32,
+    cancel: &CancelToken,
+    on_progress: impl Fn(u8),
+) -> Result<Transcript, String> {
+    let model_path = config
+        .model_path
+        .clone()
+        .map(PathBuf::from)
+        .ok_or_else(|| "No speech model is downloaded and selected".to_string())?;
+    let mut options = TranscribeOptions {
+        language: config.language.clone(),
+        ..Default::default()
+    };
+    let allowed_languages = config.allowed_languages.clone();
+    let prompt = config.prompt.clone();
+    let cancel = cancel.clone();
+
+    let task = move |models: &ModelManager, progress: &mut dyn FnMut(u8)| {
+        // The job may have waited behind another session's.
+        cancel.check()?;
+        let samples = match sample_rate {
+            WHISPER_SAMPLE_RATE => samples,
+            rate => audio::resample(&samples, rate, WHISPER_SAMPLE_RATE),
+        };
+        let (speech_segments, (speech, offset_ms)) = match vad::detect(&samples) {
+            Ok(segments) => {
+                let trimmed = vad::trim_silence(&samples, &segments);
+                (segments, trimmed)
+            }
+            Err(e) => {
+                log::warn!("VAD unavailable, transcribing untrimmed audio: {e}");
+                (Vec::new(), (&samples[..], 0))
+            }
+        };
+        if speech.is_empty() {
+            log::debug!("No speech detected in {} samples", samples.len());
+            return Ok(Transcript {
+                speech_segments,
+                ..Default::default()
+            });
+        }
+
+        let (mut segments, language_probability) = models.with_context(&model_path, |ctx| {
+            // Detect up front rather than leaving it to whisper, so the
+            // whitelist applies and the result can be stored.
+            let mut language_probability = None;
+            if options.language.is_none() {
+                let detected = ctx.detect_language(speech, &allowed_languages)?;
+                log::info!(
+                    "Detected language {} (p={:.2})",
+                    detected.code,
+                    detected.probability
+                );
+                options.language = Some(detected.code);
+                language_probability = Some(detected.probability);
+            }
+            options.initial_prompt = prompt.build(options.language.as_deref());
+            if let Some(p) = &options.initial_prompt {
+                log::debug!("Initial prompt: {p}");
+            }
+            let should_abort = || cancel.is_cancelled();
+            let segments = ctx.transcribe_with_callbacks(speech, &options, progress, &should_abort);
+            // An aborted run fails with a whisper error; report the
+            // cancellation instead.
+            cancel.check()?;
+            segments.map(|segments| (segments, language_probability))
+        })??;
+        for s in &mut segments {
+            s.start_ms += offset_ms;
+            s.end_ms += offset_ms;
+            log::debug!(
+                "segment {}-{}ms (no_speech_prob {:.2}): {}",
+                s.start_ms,
+                s.end_ms,
+                s.no_speech_prob,
+                s.text
+            );
+        }
+
+        let (segments, filtered_segments) = segment_filter::filter(segments);
+        for s in &filtered_segments {
+            log::info!("Dropped segment ({:?}): {}", s.reason, s.text);
+        }
+        let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+        Ok(Transcript {
+            text: text.trim().to_string(),
+            language: options.language,
+            language_probability,
+            confidence: confidence::assess(&segments),
+            segments,
+            speech_segments,
+            filtered_segments,
+        })
+    };
+    config.worker.run(task, on_progress).await
+}
@@ -0,0 +1,346 @@
+//! House-style normalization for Japanese text: character width, punctuation
+//! and spacing around Latin words. Whisper is inconsistent about all of them.
+
+use crate::state::{CharacterWidth, LatinSpacing, PunctuationStyle, TextNormalizationSettings};
+use unicode_normalization::UnicodeNormalization;
+
+/// Offset between full-width forms (U+FF01..U+FF5E) and ASCII.
+const FULLWIDTH_OFFSET: u32 = 0xFEE0;
+
+/// Settings with defaults resolved.
+#[derive(Debug, Clone, Copy)]
+pub struct Normalizer {
+    enabled: bool,
+    width: CharacterWidth,
+    punctuation: PunctuationStyle,
+    fix_katakana: bool,
+    spacing: LatinSpacing,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new(&TextNormalizationSettings::default())
+    }
+}
+
+impl Normalizer {
+    pub fn new(settings: &TextNormalizationSettings) -> Self {
+        Self {
+            enabled: settings.enabled.unwrap_or(false),
+            width: settings
+                .alphanumeric_width
+                .unwrap_or(CharacterWidth::Halfwidth),
+            punctuation: settings.punctuation_style.unwrap_or(PunctuationStyle::Keep),
+            fix_katakana: settings.fix_halfwidth_katakana.unwrap_or(true),
+            spacing: settings.latin_spacing.unwrap_or(LatinSpacing::Keep),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        let mut text = text.to_string();
+        if self.fix_katakana {
+            text = fullwidth_katakana(&text);
+        }
+        if self.width != CharacterWidth::Keep {
+            text = convert_width(&text, self.width);
+        }
+        text = match self.punctuation {
+            PunctuationStyle::Keep => text,
+            PunctuationStyle::Touten => punctuation(&text, '、', '。'),
+            PunctuationStyle::Comma => punctuation(&text, '，', '．'),
+        };
+        match self.spacing {
+            LatinSpacing::Keep => text,
+            LatinSpacing::Insert => insert_latin_spaces(&text),
+            LatinSpacing::Remove => remove_latin_spaces(&text),
+        }
+    }
+}
+
+/// Kana, kanji and the iteration marks that go with them.
+pub fn is_japanese(c: char) -> bool {
+    matches!(c,
+        '\u{3005}'..='\u{3007}'   // 々〆〇
+        | '\u{3040}'..='\u{30FF}' // hiragana, katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+    )
+}
+
+fn is_halfwidth_katakana(c: char) -> bool {
+    // ｡ through ﾟ, including the half-width brackets and voicing marks.
+    matches!(c, '\u{FF61}'..='\u{FF9F}')
+}
+
+/// NFKC on runs of half-width katakana only, which also folds a trailing
+/// voicing mark into its kana (ｶﾞ → ガ). NFKC on the whole text would rewrite
+/// far more than intended (① → 1).
+fn fullwidth_katakana(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+    for c in text.chars() {
+        if is_halfwidth_katakana(c) {
+            run.push(c);
+            continue;
+        }
+        if !run.is_empty() {
+            out.extend(run.nfkc());
+            run.clear();
+        }
+        out.push(c);
+    }
+    out.extend(run.nfkc());
+    out
+}
+
+/// Map letters and digits to `width`, along with the decimal point or
+/// thousands separator inside a number ("３．５" → "3.5").
+fn convert_width(text: &str, width: CharacterWidth) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    (0..chars.len())
+        .map(|i| {
+            let c = chars[i];
+            let separator = is_numeric_separator(&chars, i);
+            match width {
+                CharacterWidth::Keep => c,
+                CharacterWidth::Halfwidth if separator => match c {
+                    '．' => '.',
+                    '，' => ',',
+                    _ => c,
+                },
+                CharacterWidth::Fullwidth if separator => match c {
+                    '.' => '．',
+                    ',' => '，',
+                    _ => c,
+                },
+                CharacterWidth::Halfwidth => to_halfwidth(c),
+                CharacterWidth::Fullwidth => to_fullwidth(c),
+            }
+        })
+        .collect()
+}
+
+/// `.`, `,` or their full-width forms with a digit on both sides.
+fn is_numeric_separator(chars: &[char], i: usize) -> bool {
+    let is_digit = |c: Option<&char>| c.is_some_and(|c| to_halfwidth(*c).is_ascii_digit());
+    matches!(chars[i], '.' | ',' | '．' | '，')
+        && i > 0
+        && is_digit(chars.get(i - 1))
+        && is_digit(chars.get(i + 1))
+}
+
+fn to_halfwidth(c: char) -> char {
+    match c {
+        '０'..='９' | 'Ａ'..='Ｚ' | 'ａ'..='ｚ' => {
+            char::from_u32(c as u32 - FULLWIDTH_OFFSET).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+fn to_fullwidth(c: char) -> char {
+    if c.is_ascii_alphanumeric() {
+        char::from_u32(c as u32 + FULLWIDTH_OFFSET).unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// Use `comma` and `period` for Japanese punctuation, including ASCII `,`
+/// and `.` written directly after Japanese text. Elsewhere ASCII is left alone
+/// so English sentences keep their punctuation, and separators inside numbers
+/// are never touched.
+fn punctuation(text: &str, comma: char, period: char) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if is_numeric_separator(&chars, i) {
+            out.push(c);
+            continue;
+        }
+        let after_japanese = i > 0 && is_japanese(chars[i - 1]);
+        let mapped = match c {
+            '、' | '，' => comma,
+            '。' | '．' => period,
+            ',' if after_japanese => comma,
+            '.' if after_japanese => period,
+            _ => c,
+        };
+        out.push(mapped);
+    }
+    out
+}
+
+/// Latin letters and digits of either width.
+fn is_latin(c: char) -> bool {
+    to_halfwidth(c).is_ascii_alphanumeric()
+}
+
+/// Put a single space wherever Japanese text meets a Latin word or number.
+fn insert_latin_spaces(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prev: Option<char> = None;
+    for c in text.chars() {
+        if let Some(p) = prev {
+            if (is_japanese(p) && is_latin(c)) || (is_latin(p) && is_japanese(c)) {
+                out.push(' ');
+            }
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// Drop whitespace between Japanese text and a Latin word or number.
+fn remove_latin_spaces(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            let end = chars[i..]
+                .iter()
+                .position(|c| !c.is_whitespace())
+                .map_or(chars.len(), |n| i + n);
+            let before = out.chars().last();
+            let after = chars.get(end).copied();
+            let boundary = match (before, after) {
+                (Some(a), Some(b)) => {
+                    (is_japanese(a) && is_latin(b)) || (is_latin(a) && is_japanese(b))
+                }
+                _ => false,
+            };
+            if !boundary {
+                out.extend(&chars[i..end]);
+            }
+            i = end;
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enabled, with every conversion off unless `configure` turns it on.
+    fn normalizer(configure: impl FnOnce(&mut TextNormalizationSettings)) -> Normalizer {
+        let mut settings = TextNormalizationSettings {
+            enabled: Some(true),
+            alphanumeric_width: Some(CharacterWidth::Keep),
+            punctuation_style: Some(PunctuationStyle::Keep),
+            fix_halfwidth_katakana: Some(false),
+            latin_spacing: Some(LatinSpacing::Keep),
+        };
+        configure(&mut settings);
+        Normalizer::new(&settings)
+    }
+
+    fn check(normalizer: Normalizer, cases: &[(&str, &str)]) {
+        for &(input, expected) in cases {
+            assert_eq!(normalizer.apply(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn is_off_by_default() {
+        let text = "ｶﾀｶﾅとＡＢＣ１２３, test.";
+        assert_eq!(Normalizer::default().apply(text), text);
+        let enabled = Normalizer::new(&TextNormalizationSettings {
+            enabled: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(enabled.apply(text), "カタカナとABC123, test.");
+    }
+
+    #[test]
+    fn converts_width() {
+        check(
+            normalizer(|s| s.alphanumeric_width = Some(CharacterWidth::Halfwidth)),
+            &[
+                ("ＡＢＣ１２３", "ABC123"),
+                ("３．５と１，０００", "3.5と1,000"),
+                ("１．", "1．"),
+                ("（全角）", "（全角）"),
+            ],
+        );
+        check(
+            normalizer(|s| s.alphanumeric_width = Some(CharacterWidth::Fullwidth)),
+            &[("ABC123", "ＡＢＣ１２３"), ("3.5", "３．５"), ("a.", "ａ.")],
+        );
+        check(normalizer(|_| {}), &[("ＡB１2", "ＡB１2")]);
+    }
+
+    #[test]
+    fn converts_punctuation() {
+        check(
+            normalizer(|s| s.punctuation_style = Some(PunctuationStyle::Touten)),
+            &[
+                ("はい，そうです．", "はい、そうです。"),
+                ("はい,そうです.", "はい、そうです。"),
+                ("Yes, it is.", "Yes, it is."),
+                ("3.5円", "3.5円"),
+            ],
+        );
+        check(
+            normalizer(|s| s.punctuation_style = Some(PunctuationStyle::Comma)),
+            &[
+                ("はい、そうです。", "はい，そうです．"),
+                ("Yes, it is.", "Yes, it is."),
+            ],
+        );
+        check(
+            normalizer(|_| {}),
+            &[("はい,そうです．", "はい,そうです．")],
+        );
+    }
+
+    #[test]
+    fn fixes_halfwidth_katakana() {
+        check(
+            normalizer(|s| s.fix_halfwidth_katakana = Some(true)),
+            &[
+                ("ｶﾀｶﾅ", "カタカナ"),
+                ("ｶﾞｯｺｳ", "ガッコウ"),
+                ("ﾊﾟﾝと①", "パンと①"),
+                ("｢ｱ｣", "「ア」"),
+            ],
+        );
+        check(normalizer(|_| {}), &[("ｶﾀｶﾅ", "ｶﾀｶﾅ")]);
+    }
+
+    #[test]
+    fn adjusts_latin_spacing() {
+        check(
+            normalizer(|s| s.latin_spacing = Some(LatinSpacing::Insert)),
+            &[
+                ("日本語とEnglishの文", "日本語と English の文"),
+                ("iPhone15を買った", "iPhone15 を買った"),
+                ("既に Tauri です", "既に Tauri です"),
+                ("English only", "English only"),
+            ],
+        );
+        check(
+            normalizer(|s| s.latin_spacing = Some(LatinSpacing::Remove)),
+            &[
+                ("日本語と English の文", "日本語とEnglishの文"),
+                ("日本語と  ３つ", "日本語と３つ"),
+                ("English only", "English only"),
+                ("。 Next", "。 Next"),
+            ],
+        );
+        check(
+            normalizer(|_| {}),
+            &[("日本語とEnglish", "日本語とEnglish")],
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Whisper initial prompt built from the user's vocabulary and recent
+//! transcriptions. Conditioning on what the user said before steers kanji
+//! choice and the spelling of names, as the Electron `WhisperProvider` does.
+
+use crate::db::Database;
+use crate::pipeline::streaming::append_text;
+use crate::pipeline::vocabulary::Vocabulary;
+use crate::state::AppSettingsData;
+
+/// Used when `TranscriptionSettings.initial_prompt_length` is unset. Whisper
+/// only keeps about 224 prompt tokens, roughly this many Japanese characters.
+pub const DEFAULT_PROMPT_LENGTH: usize = 200;
+
+/// Used when `TranscriptionSettings.initial_prompt_history` is unset.
+pub const DEFAULT_PROMPT_HISTORY: u32 = 10;
+
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    language: Option<String>,
+    text: String,
+}
+
+/// Prompt material snapshotted with the pipeline settings.
+#[derive(Debug, Clone, Default)]
+pub struct PromptContext {
+    /// Newest first.
+    history: Vec<HistoryEntry>,
+    vocabulary: Vec<String>,
+    max_chars: usize,
+}
+
+impl PromptContext {
+    pub fn load(
+        db: &Database,
+        settings: &AppSettingsData,
+        vocabulary: &Vocabulary,
+    ) -> Result<Self, String> {
+        let transcription = settings.transcription.as_ref();
+        let max_chars = transcription
+            .and_then(|t| t.initial_prompt_length)
+            .map_or(DEFAULT_PROMPT_LENGTH, |n| n as usize);
+        if max_chars == 0 {
+            return Ok(Self::default());
+        }
+        let history_limit = transcription
+            .and_then(|t| t.initial_prompt_history)
+            .unwrap_or(DEFAULT_PROMPT_HISTORY);
+
+        let history = db
+            .get_transcriptions(i64::from(history_limit), 0)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|t| !t.text.trim().is_empty())
+            .map(|t| HistoryEntry {
+                language: t.language,
+                text: t.text.trim().to_string(),
+            })
+            .collect();
+        Ok(Self {
+            history,
+            vocabulary: vocabulary.terms().to_vec(),
+            max_chars,
+        })
+    }
+
+    /// Prompt for audio in `language`, or `None` if there is no context.
+    ///
+    /// Vocabulary terms come first and take priority; recent transcriptions
+    /// fill the remaining length. Only transcriptions in the same language are
+    /// used, unless the language is unknown, and the oldest text is cut first.
+    pub fn build(&self, language: Option<&str>) -> Option<String> {
+        self.build_continuing(language, "")
+    }
+
+    /// [`build`](Self::build) for audio that follows `preceding`, text
+    /// already transcribed in the same session, which counts as the newest
+    /// transcription.
+    pub fn build_continuing(&self, language: Option<&str>, preceding: &str) -> Option<String> {
+        let (separator, sentence_end) = match language {
+            Some("ja") | Some("zh") => ("、", "。"),
+            _ => (", ", ". "),
+        };
+
+        let mut prompt = String::new();
+        let mut prompt_chars = 0;
+        for term in &self.vocabulary {
+            let sep = if prompt.is_empty() { "" } else { separator };
+            let chars = sep.chars().count() + term.chars().count();
+            if prompt_chars + chars > self.max_chars {
+                break;
+            }
+            prompt.push_str(sep);
+            prompt.push_str(term);
+            prompt_chars += chars;
+        }
+
+        let sep = if prompt.is_empty() { "" } else { sentence_end };
+        let budget = self
+            .max_chars
+            .saturating_sub(prompt_chars + sep.chars().count());
+        let history = self.recent_text(language, preceding, budget);
+        if !history.is_empty() {
+            prompt.push_str(sep);
+            prompt.push_str(&history);
+        }
+        let prompt = prompt.trim();
+        (!prompt.is_empty()).then(|| prompt.to_string())
+    }
+
+    /// The last `max_chars` characters of `preceding` and recent
+    /// transcriptions in `language`.
+    fn recent_text(&self, language: Option<&str>, preceding: &str, max_chars: usize) -> String {
+        let mut recent: Vec<&str> = Vec::new();
+        let mut chars = 0;
+        let preceding = preceding.trim();
+        if !preceding.is_empty() {
+            recent.push(preceding);
+            chars += preceding.chars().count();
+        }
+        for entry in &self.history {
+            if chars >= max_chars {
+                break;
+            }
+            if language.is_some() && entry.language.as_deref() != language {
+                continue;
+            }
+            recent.push(&entry.text);
+            chars += entry.text.chars().count();
+        }
+
+        let mut text = String::new();
+        for entry in recent.iter().rev() {
+            append_text(&mut text, entry);
+        }
+        let chars = text.chars().count();
+        if chars > max_chars {
+            let cut = text
+                .char_indices()
+                .nth(chars - max_chars)
+                .map_or(text.len(), |(i, _)| i);
+            text.drain(..cut);
+        }
+        text.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(history: &[(&str, &str)], vocabulary: &[&str], max_chars: usize) -> PromptContext {
+        PromptContext {
+            history: history
+                .iter()
+                .map(|&(language, text)| HistoryEntry {
+                    language: Some(language.to_string()),
+                    text: text.to_string(),
+                })
+                .collect(),
+            vocabulary: vocabulary.iter().map(|t| t.to_string()).collect(),
+            max_chars,
+        }
+    }
+
+    #[test]
+    fn puts_vocabulary_before_recent_text_of_the_same_language() {
+        let context = context(
+            &[
+                ("en", "Newest note."),
+                ("ja", "日本語です。"),
+                ("en", "Older note."),
+            ],
+            &["Kotoba", "Tauri"],
+            200,
+        );
+        assert_eq!(
+            context.build(Some("en")).as_deref(),
+            Some("Kotoba, Tauri. Older note. Newest note.")
+        );
+        assert_eq!(
+            context.build(Some("ja")).as_deref(),
+            Some("Kotoba、Tauri。日本語です。")
+        );
+    }
+
+    #[test]
+    fn continues_from_the_session_text() {
+        let context = context(&[("en", "Earlier note.")], &["Kotoba"], 200);
+        assert_eq!(
+            context
+                .build_continuing(Some("en"), "So far, so good.")
+                .as_deref(),
+            Some("Kotoba. Earlier note. So far, so good.")
+        );
+
+        // The session text is newest, so older text is cut first.
+        let context = PromptContext {
+            max_chars: 20,
+            ..context
+        };
+        assert_eq!(
+            context
+                .build_continuing(Some("en"), "So far, so good.")
+                .as_deref(),
+            Some("Kotoba. ar, so good.")
+        );
+    }
+
+    #[test]
+    fn a_zero_length_disables_the_prompt() {
+        let context = context(&[("en", "Earlier note.")], &["Kotoba"], 0);
+        assert_eq!(context.build_continuing(Some("en"), "So far."), None);
+    }
+}
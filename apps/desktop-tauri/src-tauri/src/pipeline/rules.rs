@@ -0,0 +1,263 @@
+//! User-defined find/replace rules and snippet expansion, applied in order to
+//! the final text.
+
+use crate::db::{RuleKind, TextRule};
+use regex::{NoExpand, Regex};
+use serde::Serialize;
+
+/// A rule that changed the text, recorded in `Transcription.meta`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FiredRule {
+    pub id: i64,
+    pub kind: RuleKind,
+    /// Number of matches replaced.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    id: i64,
+    kind: RuleKind,
+    regex: Regex,
+    replacement: String,
+}
+
+/// Enabled rules, compiled in order.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// Compile enabled rules. A rule that fails to compile is skipped with a
+    /// warning rather than blocking every transcription.
+    pub fn new(rules: Vec<TextRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter(|r| r.enabled)
+            .filter_map(|r| match compile(r.kind, &r.pattern) {
+                Ok(regex) => Some(CompiledRule {
+                    id: r.id,
+                    kind: r.kind,
+                    regex,
+                    replacement: r.replacement,
+                }),
+                Err(e) => {
+                    log::warn!("Skipping text rule {}: {e}", r.id);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Apply every rule in turn, each to the output of the previous one.
+    pub fn apply(&self, text: &str) -> (String, Vec<FiredRule>) {
+        let mut text = text.to_string();
+        let mut fired = Vec::new();
+        for rule in &self.rules {
+            let count = rule.regex.find_iter(&text).count();
+            if count == 0 {
+                continue;
+            }
+            text = match rule.kind {
+                RuleKind::Regex => rule.regex.replace_all(&text, rule.replacement.as_str()),
+                RuleKind::Literal | RuleKind::Snippet => {
+                    rule.regex.replace_all(&text, NoExpand(&rule.replacement))
+                }
+            }
+            .into_owned();
+            fired.push(FiredRule {
+                id: rule.id,
+                kind: rule.kind,
+                count,
+            });
+        }
+        (text, fired)
+    }
+}
+
+/// Build the matcher for a rule; also used to validate rules as they are saved.
+///
+/// Snippet triggers match case-insensitively, as whole words for Latin text,
+/// and swallow the punctuation whisper tends to put after a lone phrase.
+pub fn compile(kind: RuleKind, pattern: &str) -> Result<Regex, String> {
+    if pattern.trim().is_empty() && (kind == RuleKind::Snippet || pattern.is_empty()) {
+        return Err("Pattern must not be empty".to_string());
+    }
+    let source = match kind {
+        RuleKind::Literal => regex::escape(pattern),
+        RuleKind::Regex => pattern.to_string(),
+        RuleKind::Snippet => {
+            let trigger = regex::escape(pattern.trim());
+            let word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+            let start = if word(pattern.trim().chars().next()) {
+                r"\b"
+            } else {
+                ""
+            };
+            let end = if word(pattern.trim().chars().last()) {
+                r"\b"
+            } else {
+                ""
+            };
+            format!(r"(?i){start}{trigger}{end}[。．.、,！!？?]?")
+        }
+    };
+    Regex::new(&source).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RuleKind::{Literal, Regex as Re, Snippet};
+
+    /// Enabled rules with ids from 1, in order.
+    fn rules(rules: &[(RuleKind, &str, &str)]) -> Vec<TextRule> {
+        rules
+            .iter()
+            .enumerate()
+            .map(|(i, &(kind, pattern, replacement))| TextRule {
+                id: i as i64 + 1,
+                kind,
+                pattern: pattern.to_string(),
+                replacement: replacement.to_string(),
+                position: i as i64,
+                enabled: true,
+                created_at: 0,
+                updated_at: 0,
+            })
+            .collect()
+    }
+
+    fn rule_set(rules: &[(RuleKind, &str, &str)]) -> RuleSet {
+        RuleSet::new(self::rules(rules))
+    }
+
+    #[test]
+    fn applies_rules_in_order() {
+        type Case<'a> = (
+            &'a str,
+            &'a [(RuleKind, &'a str, &'a str)],
+            &'a str,
+            &'a str,
+            &'a [(i64, usize)],
+        );
+        let cases: &[Case] = &[
+            ("no rules", &[], "a.b", "a.b", &[]),
+            (
+                "literal escapes metacharacters",
+                &[(Literal, "a.b", "x")],
+                "a.b axb a.b",
+                "x axb x",
+                &[(1, 2)],
+            ),
+            (
+                "literal replacement is not expanded",
+                &[(Literal, "cost", "$1")],
+                "cost",
+                "$1",
+                &[(1, 1)],
+            ),
+            (
+                "regex with groups",
+                &[(Re, r"(\d+)円", "¥$1")],
+                "100円と20円",
+                "¥100と¥20",
+                &[(1, 2)],
+            ),
+            (
+                "regex with named groups",
+                &[(Re, r"(?P<h>\d+)時(?P<m>\d+)分", "${h}:${m}")],
+                "9時30分",
+                "9:30",
+                &[(1, 1)],
+            ),
+            (
+                "later rules see earlier output",
+                &[(Literal, "cat", "dog"), (Literal, "dog", "wolf")],
+                "cat",
+                "wolf",
+                &[(1, 1), (2, 1)],
+            ),
+            (
+                "earlier rules can starve later ones",
+                &[(Literal, "dog", "wolf"), (Literal, "cat", "dog")],
+                "cat",
+                "dog",
+                &[(2, 1)],
+            ),
+            (
+                "rules that do not match are not recorded",
+                &[(Literal, "x", "y"), (Re, r"\s+", " ")],
+                "a  b   c",
+                "a b c",
+                &[(2, 2)],
+            ),
+            (
+                "snippet expands case-insensitively",
+                &[(Snippet, "my address", "1-2-3 Shibuya")],
+                "Send it to My Address.",
+                "Send it to 1-2-3 Shibuya",
+                &[(1, 1)],
+            ),
+            (
+                "snippet matches whole Latin words only",
+                &[(Snippet, "sig", "-- Kotoba")],
+                "signal sig",
+                "signal -- Kotoba",
+                &[(1, 1)],
+            ),
+            (
+                "snippet swallows trailing Japanese punctuation",
+                &[(Snippet, "署名", "山田太郎")],
+                "以上、署名。",
+                "以上、山田太郎",
+                &[(1, 1)],
+            ),
+            (
+                "snippet body is not expanded",
+                &[(Snippet, "price", "$5")],
+                "price",
+                "$5",
+                &[(1, 1)],
+            ),
+        ];
+        for &(name, rules, input, output, fired) in cases {
+            let (text, fired_rules) = rule_set(rules).apply(input);
+            assert_eq!(text, output, "{name}");
+            let fired_rules: Vec<(i64, usize)> =
+                fired_rules.iter().map(|r| (r.id, r.count)).collect();
+            assert_eq!(fired_rules, fired, "{name}");
+        }
+    }
+
+    #[test]
+    fn records_the_kind_of_fired_rules() {
+        let rules = rule_set(&[(Literal, "a", "b"), (Re, "b+", "c"), (Snippet, "c", "d")]);
+        let (text, fired) = rules.apply("aa");
+        assert_eq!(text, "d");
+        let kinds: Vec<RuleKind> = fired.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, [Literal, Re, Snippet]);
+    }
+
+    #[test]
+    fn skips_disabled_and_invalid_rules() {
+        let mut rules = rules(&[(Re, "(", "x"), (Literal, "a", "b"), (Literal, "b", "c")]);
+        rules[2].enabled = false;
+        let (text, fired) = RuleSet::new(rules).apply("a(");
+        assert_eq!(text, "b(");
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].id, 2);
+    }
+
+    #[test]
+    fn rejects_empty_patterns() {
+        assert!(compile(Literal, "").is_err());
+        assert!(compile(Re, "").is_err());
+        assert!(compile(Snippet, "  ").is_err());
+        assert!(compile(Literal, " ").is_ok());
+        assert!(compile(Re, "(").is_err());
+    }
+}
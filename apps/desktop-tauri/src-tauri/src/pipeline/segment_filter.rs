@@ -0,0 +1,177 @@
+//! Drops whisper segments that are most likely hallucinated on silence.
+//! Port of the Electron `pipeline/utils/segment-filter.ts`.
+
+use crate::whisper::Segment;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
+
+/// Above this, whisper is confident there is no speech at all.
+const NO_SPEECH_THRESHOLD: f32 = 0.8;
+/// Lower bar for text that matches a known hallucination phrase.
+const HALLUCINATION_THRESHOLD: f32 = 0.4;
+
+/// Shared with the Electron app. The generated file holds one string literal
+/// per line, without escapes.
+static PHRASES_SOURCE: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../desktop/src/data/hallucination-phrases.ts"
+));
+
+static PHRASES: OnceLock<HashSet<String>> = OnceLock::new();
+
+fn phrases() -> &'static HashSet<String> {
+    PHRASES.get_or_init(|| {
+        PHRASES_SOURCE
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix('"')?.strip_suffix("\","))
+            .map(normalize)
+            .filter(|phrase| !phrase.is_empty())
+            .collect()
+    })
+}
+
+/// Why a segment was dropped.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DropReason {
+    NoSpeech,
+    KnownHallucination,
+}
+
+/// A dropped segment, kept in `Transcription.meta` for auditing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilteredSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub no_speech_prob: f32,
+    pub reason: DropReason,
+}
+
+/// Split segments into those to keep and those dropped.
+pub fn filter(segments: Vec<Segment>) -> (Vec<Segment>, Vec<FilteredSegment>) {
+    let mut kept = Vec::with_capacity(segments.len());
+    let mut dropped = Vec::new();
+    for segment in segments {
+        match drop_reason(&segment) {
+            Some(reason) => dropped.push(FilteredSegment {
+                start_ms: segment.start_ms,
+                end_ms: segment.end_ms,
+                text: segment.text,
+                no_speech_prob: segment.no_speech_prob,
+                reason,
+            }),
+            None => kept.push(segment),
+        }
+    }
+    (kept, dropped)
+}
+
+/// 1. `no_speech_prob > 0.8` → drop.
+/// 2. `no_speech_prob > 0.4` and the text is a known hallucination → drop.
+fn drop_reason(segment: &Segment) -> Option<DropReason> {
+    if segment.no_speech_prob > NO_SPEECH_THRESHOLD {
+        Some(DropReason::NoSpeech)
+    } else if segment.no_speech_prob > HALLUCINATION_THRESHOLD
+        && phrases().contains(&normalize(&segment.text))
+    {
+        Some(DropReason::KnownHallucination)
+    } else {
+        None
+    }
+}
+
+/// NFC, lowercase, and punctuation folded into single spaces.
+///
+/// This deliberately goes further than the Electron `normalizeText`, which
+/// only trims: the phrase list was generated without punctuation, while
+/// whisper ends sentences with "." or "。", so Electron never matches
+/// "ご視聴ありがとうございました。". Both sides go through the same folding,
+/// so every text Electron matches still matches here.
+fn normalize(text: &str) -> String {
+    let folded: String = text
+        .nfc()
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, no_speech_prob: f32) -> Segment {
+        Segment {
+            start_ms: 0,
+            end_ms: 1_000,
+            text: text.to_string(),
+            no_speech_prob,
+            avg_logprob: 0.0,
+            tokens: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn applies_both_thresholds() {
+        let cases = [
+            ("Let's get started.", 0.81, Some(DropReason::NoSpeech)),
+            ("Let's get started.", 0.8, None),
+            ("Let's get started.", 0.5, None),
+            ("Thank you for watching", 0.81, Some(DropReason::NoSpeech)),
+            (
+                "Thank you for watching",
+                0.41,
+                Some(DropReason::KnownHallucination),
+            ),
+            ("Thank you for watching", 0.4, None),
+        ];
+        for (text, prob, expected) in cases {
+            assert_eq!(
+                drop_reason(&segment(text, prob)),
+                expected,
+                "{text} @ {prob}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_phrases_regardless_of_form_case_and_punctuation() {
+        let cases = [
+            // Electron's exact form.
+            "thank you for watching",
+            " Thank You For Watching ",
+            "Thank you for watching!",
+            "ご視聴ありがとうございました。",
+            // "é" as "e" plus a combining accent.
+            "'he\u{301}sitez pas à la partager avec vous merci",
+        ];
+        for text in cases {
+            assert_eq!(
+                drop_reason(&segment(text, 0.5)),
+                Some(DropReason::KnownHallucination),
+                "{text}"
+            );
+        }
+        assert_eq!(drop_reason(&segment("Thank you for waiting.", 0.5)), None);
+    }
+
+    #[test]
+    fn splits_kept_and_dropped_segments() {
+        let (kept, dropped) = filter(vec![
+            segment("Hello there.", 0.1),
+            segment("ご視聴ありがとうございました", 0.6),
+            segment("…", 0.9),
+        ]);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].text, "Hello there.");
+        let reasons: Vec<_> = dropped.iter().map(|s| s.reason).collect();
+        assert_eq!(
+            reasons,
+            [DropReason::KnownHallucination, DropReason::NoSpeech]
+        );
+    }
+}
@@ -0,0 +1,528 @@
+//! Live partial transcription while a dictation session is recording.
+//!
+//! Mirrors the frame aggregation of the Electron `WhisperProvider`: PCM is cut
+//! into 512-sample frames and buffered until 3 s of silence (or 30 s of audio),
+//! at which point the window is committed as stable text. In between, the
+//! uncommitted window is re-decoded in the background as tentative text.
+
+use crate::audio::Resampler;
+use crate::model_manager::ModelManager;
+use crate::pipeline::cancel::CancelToken;
+use crate::pipeline::prompt::PromptContext;
+use crate::pipeline::segment_filter;
+use crate::pipeline::PipelineConfig;
+use crate::vad::{SileroVad, FRAME_SIZE};
+use crate::whisper::{TranscribeOptions, WHISPER_SAMPLE_RATE};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+/// Minimum buffered audio before a silence cutoff commits the window.
+const MIN_AUDIO_DURATION_MS: usize = 500;
+/// Silence that ends an utterance.
+const MAX_SILENCE_DURATION_MS: usize = 3_000;
+/// Commit regardless of silence once the window reaches whisper's 30 s input.
+const MAX_WINDOW_DURATION_MS: usize = 30_000;
+/// How much new speech triggers another tentative decode.
+const PARTIAL_INTERVAL_MS: usize = 1_000;
+/// VAD probability above which a frame counts as speech.
+const SPEECH_PROBABILITY_THRESHOLD: f32 = 0.2;
+/// Frame RMS above which a frame counts as speech when the VAD is unavailable.
+const SPEECH_RMS_THRESHOLD: f32 = 0.01;
+
+/// Payload of the `transcription-partial` event.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionPartial {
+    pub session_id: String,
+    /// Text of committed windows; will not change for the rest of the session.
+    pub stable_text: String,
+    /// Best guess for the window still being spoken; replaced on every update.
+    pub tentative_text: String,
+}
+
+enum Job {
+    Partial(Vec<f32>),
+    Commit(Vec<f32>),
+}
+
+/// Per-session buffering on the command side. Decoding happens on a worker
+/// thread that stops once the session is dropped or cancelled.
+pub struct StreamingSession {
+    session_id: String,
+    /// Converts chunks to 16 kHz, carrying the kernel across chunk
+    /// boundaries.
+    resampler: Resampler,
+    /// Everything received so far at 16 kHz, for the final full-quality pass.
+    session_audio: Vec<f32>,
+    /// Tail that does not yet fill a frame.
+    pending: Vec<f32>,
+    /// Frames of the current, uncommitted window.
+    window: Vec<f32>,
+    window_has_speech: bool,
+    silence_frames: usize,
+    speech_samples_since_partial: usize,
+    vad: Option<SileroVad>,
+    jobs: Sender<Job>,
+    /// Fired on drop so the worker abandons queued jobs and any decode in
+    /// progress.
+    shutdown: CancelToken,
+    text: Arc<Mutex<TranscriptionPartial>>,
+}
+
+impl StreamingSession {
+    /// Start a session and its decoding worker. Chunks are expected at
+    /// `config.sample_rate` and converted to 16 kHz on arrival. Cancelling
+    /// `cancel` stops live decoding.
+    pub fn start(
+        app: tauri::AppHandle,
+        session_id: String,
+        config: &PipelineConfig,
+        models: ModelManager,
+        cancel: CancelToken,
+    ) -> Self {
+        let (jobs, receiver) = mpsc::channel();
+        let session = Self::new(session_id, config.sample_rate, jobs);
+        let worker = Worker {
+            app,
+            model_path: config.model_path.as_ref().map(PathBuf::from),
+            models,
+            language: config.language.clone(),
+            allowed_languages: config.allowed_languages.clone(),
+            prompt: config.prompt.clone(),
+            cancel,
+            shutdown: session.shutdown.clone(),
+            text: Arc::clone(&session.text),
+        };
+        std::thread::Builder::new()
+            .name("kotoba-streaming".to_string())
+            .spawn(move || worker.run(receiver))
+            .expect("failed to spawn streaming worker");
+        session
+    }
+
+    /// Buffering only; decode jobs go to `jobs`.
+    fn new(session_id: String, input_rate: u32, jobs: Sender<Job>) -> Self {
+        Self {
+            text: Arc::new(Mutex::new(TranscriptionPartial {
+                session_id: session_id.clone(),
+                ..Default::default()
+            })),
+            session_id,
+            resampler: Resampler::new(input_rate, WHISPER_SAMPLE_RATE),
+            session_audio: Vec::new(),
+            pending: Vec::new(),
+            window: Vec::new(),
+            window_has_speech: false,
+            silence_frames: 0,
+            speech_samples_since_partial: 0,
+            vad: SileroVad::new()
+                .map_err(|e| log::warn!("Falling back to energy-based speech detection: {e}"))
+                .ok(),
+            jobs,
+            shutdown: CancelToken::default(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Current stable + tentative text.
+    pub fn current_text(&self) -> String {
+        let text = self.text.lock().unwrap_or_else(|e| e.into_inner());
+        let mut combined = text.stable_text.clone();
+        append_text(&mut combined, &text.tentative_text);
+        combined
+    }
+
+    /// Take every sample received this session, at 16 kHz.
+    pub fn take_audio(&mut self) -> Vec<f32> {
+        let tail = self.resampler.finish();
+        self.session_audio.extend(tail);
+        std::mem::take(&mut self.session_audio)
+    }
+
+    /// Buffer a chunk from the renderer, scheduling background decodes as
+    /// frames accumulate.
+    pub fn push_chunk(&mut self, chunk: &[f32]) {
+        let chunk = self.resampler.process(chunk);
+        self.session_audio.extend_from_slice(&chunk);
+        self.pending.extend_from_slice(&chunk);
+
+        let n_frames = self.pending.len() / FRAME_SIZE;
+        let frames: Vec<f32> = self.pending.drain(..n_frames * FRAME_SIZE).collect();
+        for frame in frames.chunks_exact(FRAME_SIZE) {
+            self.push_frame(frame);
+        }
+    }
+
+    fn push_frame(&mut self, frame: &[f32]) {
+        self.window.extend_from_slice(frame);
+        if self.is_speech(frame) {
+            self.silence_frames = 0;
+            self.window_has_speech = true;
+            self.speech_samples_since_partial += frame.len();
+        } else {
+            self.silence_frames += 1;
+        }
+
+        if self.should_commit() {
+            let window = std::mem::take(&mut self.window);
+            // A window of pure silence would only invite hallucinations.
+            if self.window_has_speech {
+                let _ = self.jobs.send(Job::Commit(window));
+            }
+            self.window_has_speech = false;
+            self.silence_frames = 0;
+            self.speech_samples_since_partial = 0;
+        } else if self.speech_samples_since_partial >= ms_to_samples(PARTIAL_INTERVAL_MS) {
+            self.speech_samples_since_partial = 0;
+            let _ = self.jobs.send(Job::Partial(self.window.clone()));
+        }
+    }
+
+    fn is_speech(&mut self, frame: &[f32]) -> bool {
+        match self.vad.as_mut().map(|vad| vad.process_frame(frame)) {
+            Some(Ok(prob)) => prob > SPEECH_PROBABILITY_THRESHOLD,
+            Some(Err(e)) => {
+                log::warn!("VAD failed, falling back to energy-based detection: {e}");
+                self.vad = None;
+                is_loud(frame)
+            }
+            None => is_loud(frame),
+        }
+    }
+
+    fn should_commit(&self) -> bool {
+        let window_samples = self.window.len();
+        let silence_samples = self.silence_frames * FRAME_SIZE;
+        (window_samples >= ms_to_samples(MIN_AUDIO_DURATION_MS)
+            && silence_samples > ms_to_samples(MAX_SILENCE_DURATION_MS))
+            || window_samples > ms_to_samples(MAX_WINDOW_DURATION_MS)
+    }
+}
+
+impl Drop for StreamingSession {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+struct Worker {
+    app: tauri::AppHandle,
+    /// Cleared when the model fails to load, which ends live decoding.
+    model_path: Option<PathBuf>,
+    /// Shared with the final pass, so the model is loaded once.
+    models: ModelManager,
+    language: Option<String>,
+    /// Whitelist for auto-detection when `language` is `None`.
+    allowed_languages: Vec<String>,
+    prompt: PromptContext,
+    /// The recording session's token, fired by `cancel_session`.
+    cancel: CancelToken,
+    shutdown: CancelToken,
+    text: Arc<Mutex<TranscriptionPartial>>,
+}
+
+impl Worker {
+    /// Process jobs until the session is dropped or cancelled.
+    fn run(mut self, jobs: Receiver<Job>) {
+        let (cancel, shutdown) = (self.cancel.clone(), self.shutdown.clone());
+        dispatch(
+            jobs,
+            &|| cancel.is_cancelled() || shutdown.is_cancelled(),
+            &mut |job| match job {
+                Job::Commit(samples) => self.commit(&samples),
+                Job::Partial(samples) => self.partial(&samples),
+            },
+        );
+    }
+
+    fn stopped(&self) -> bool {
+        self.cancel.is_cancelled() || self.shutdown.is_cancelled()
+    }
+
+    fn commit(&mut self, samples: &[f32]) {
+        let Some(text) = self.decode(samples) else {
+            return;
+        };
+        let snapshot = {
+            let mut state = self.text.lock().unwrap_or_else(|e| e.into_inner());
+            append_text(&mut state.stable_text, &text);
+            state.tentative_text.clear();
+            state.clone()
+        };
+        let _ = self.app.emit("transcription-partial", &snapshot);
+    }
+
+    fn partial(&mut self, samples: &[f32]) {
+        let Some(text) = self.decode(samples) else {
+            return;
+        };
+        let snapshot = {
+            let mut state = self.text.lock().unwrap_or_else(|e| e.into_inner());
+            state.tentative_text = text;
+            state.clone()
+        };
+        let _ = self.app.emit("transcription-partial", &snapshot);
+    }
+
+    fn decode(&mut self, samples: &[f32]) -> Option<String> {
+        let path = self.model_path.clone()?;
+        let stable_text = {
+            let state = self.text.lock().unwrap_or_else(|e| e.into_inner());
+            state.stable_text.clone()
+        };
+        let decoded = self.models.with_context(&path, |ctx| {
+            // Only an explicit whitelist needs detecting here; otherwise
+            // whisper picks the language itself.
+            let language = match &self.language {
+                None if !self.allowed_languages.is_empty() => {
+                    match ctx.detect_language(samples, &self.allowed_languages) {
+                        Ok(detected) => Some(detected.code),
+                        Err(e) => {
+                            log::warn!("Language detection failed: {e}");
+                            None
+                        }
+                    }
+                }
+                language => language.clone(),
+            };
+            // Condition on what has been committed, as the Electron provider
+            // does, after the vocabulary and history the final pass uses.
+            let initial_prompt = self
+                .prompt
+                .build_continuing(language.as_deref(), &stable_text);
+            let options = TranscribeOptions {
+                language,
+                initial_prompt,
+                ..Default::default()
+            };
+            ctx.transcribe_with_callbacks(samples, &options, &mut |_| {}, &|| self.stopped())
+        });
+        if self.stopped() {
+            return None;
+        }
+        match decoded {
+            Ok(Ok(segments)) => {
+                let (segments, _) = segment_filter::filter(segments);
+                let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+                Some(text.trim().to_string())
+            }
+            Ok(Err(e)) => {
+                log::warn!("Partial transcription failed: {e}");
+                None
+            }
+            Err(e) => {
+                log::warn!("Streaming transcription disabled: {e}");
+                self.model_path = None;
+                None
+            }
+        }
+    }
+}
+
+/// Hand queued jobs to `handle` until the sender is dropped or `stopped`
+/// returns `true`; jobs still queued then are discarded.
+fn dispatch(jobs: Receiver<Job>, stopped: &dyn Fn() -> bool, handle: &mut dyn FnMut(Job)) {
+    while let Ok(job) = jobs.recv() {
+        // Decoding is slower than real time on small machines; when jobs
+        // pile up, every commit still runs but only the newest partial.
+        let mut batch = vec![job];
+        batch.extend(jobs.try_iter());
+        let last = batch.len() - 1;
+        for (i, job) in batch.into_iter().enumerate() {
+            if stopped() {
+                return;
+            }
+            match job {
+                Job::Partial(_) if i != last => {}
+                job => handle(job),
+            }
+        }
+    }
+}
+
+fn is_loud(frame: &[f32]) -> bool {
+    let energy = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+    energy.sqrt() > SPEECH_RMS_THRESHOLD
+}
+
+/// Append `text`, separating with a space only between Latin words;
+/// Japanese runs on without spaces.
+pub(crate) fn append_text(target: &mut String, text: &str) {
+    let needs_space = matches!(
+        (target.chars().last(), text.chars().next()),
+        (Some(a), Some(b)) if a.is_ascii_graphic() && b.is_ascii_alphanumeric()
+    );
+    if needs_space {
+        target.push(' ');
+    }
+    target.push_str(text);
+}
+
+fn ms_to_samples(ms: usize) -> usize {
+    ms * WHISPER_SAMPLE_RATE as usize / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> (StreamingSession, Receiver<Job>) {
+        let (jobs, receiver) = mpsc::channel();
+        let mut session = StreamingSession::new("s".to_string(), WHISPER_SAMPLE_RATE, jobs);
+        // Energy-based detection keeps the tests independent of the model.
+        session.vad = None;
+        (session, receiver)
+    }
+
+    /// Frames needed to reach `ms`.
+    fn frames(ms: usize) -> usize {
+        ms_to_samples(ms).div_ceil(FRAME_SIZE)
+    }
+
+    fn tone(frames: usize) -> Vec<f32> {
+        (0..frames * FRAME_SIZE)
+            .map(|i| 0.5 * (i as f32 * 0.2).sin())
+            .collect()
+    }
+
+    fn silence(frames: usize) -> Vec<f32> {
+        vec![0.0; frames * FRAME_SIZE]
+    }
+
+    /// Jobs as (kind, window length in frames).
+    type Queued = Vec<(&'static str, usize)>;
+
+    fn jobs(receiver: &Receiver<Job>) -> Queued {
+        receiver
+            .try_iter()
+            .map(|job| match job {
+                Job::Partial(samples) => ("partial", samples.len() / FRAME_SIZE),
+                Job::Commit(samples) => ("commit", samples.len() / FRAME_SIZE),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn windows_and_partials() {
+        let partial = frames(PARTIAL_INTERVAL_MS);
+        // Silence must exceed the limit, so one frame past it.
+        let cutoff = ms_to_samples(MAX_SILENCE_DURATION_MS) / FRAME_SIZE + 1;
+        let max_window = ms_to_samples(MAX_WINDOW_DURATION_MS) / FRAME_SIZE + 1;
+        let cases: Vec<(&str, Vec<Vec<f32>>, Queued)> = vec![
+            ("silence never commits", vec![silence(1_000)], vec![]),
+            (
+                "speech waits for enough silence",
+                vec![tone(partial - 1), silence(cutoff - 1)],
+                vec![],
+            ),
+            (
+                "a partial per second of speech",
+                vec![tone(2 * partial + partial / 2)],
+                vec![("partial", partial), ("partial", 2 * partial)],
+            ),
+            (
+                "silence commits the window",
+                vec![tone(partial - 1), silence(cutoff)],
+                vec![("commit", partial - 1 + cutoff)],
+            ),
+            (
+                "speech after a commit starts a new window",
+                vec![tone(partial), silence(cutoff), tone(partial)],
+                vec![
+                    ("partial", partial),
+                    ("commit", partial + cutoff),
+                    ("partial", partial),
+                ],
+            ),
+            (
+                "long speech commits at 30 s",
+                vec![tone(max_window)],
+                (1..)
+                    .map(|n| n * partial)
+                    .take_while(|&n| n < max_window)
+                    .map(|n| ("partial", n))
+                    .chain([("commit", max_window)])
+                    .collect(),
+            ),
+        ];
+
+        for (name, chunks, expected) in cases {
+            let (mut session, receiver) = session();
+            for chunk in &chunks {
+                // Chunks that do not fill whole frames exercise the tail.
+                for part in chunk.chunks(1_000) {
+                    session.push_chunk(part);
+                }
+            }
+            assert_eq!(jobs(&receiver), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn cancel_stops_further_decoding() {
+        let (sender, receiver) = mpsc::channel();
+        for len in 1..=3 {
+            sender.send(Job::Commit(vec![0.0; len])).unwrap();
+        }
+        drop(sender);
+
+        let cancel = CancelToken::default();
+        let mut decoded = Vec::new();
+        dispatch(receiver, &|| cancel.is_cancelled(), &mut |job| {
+            if let Job::Commit(samples) = job {
+                decoded.push(samples.len());
+            }
+            cancel.cancel();
+        });
+        assert_eq!(decoded, vec![1]);
+    }
+
+    #[test]
+    fn dropping_the_session_stops_its_worker() {
+        let (session, _receiver) = session();
+        let shutdown = session.shutdown.clone();
+        drop(session);
+        assert!(shutdown.is_cancelled());
+    }
+
+    #[test]
+    fn backlog_runs_every_commit_but_only_the_newest_partial() {
+        let (sender, receiver) = mpsc::channel();
+        sender.send(Job::Partial(vec![0.0; 1])).unwrap();
+        sender.send(Job::Commit(vec![0.0; 2])).unwrap();
+        sender.send(Job::Partial(vec![0.0; 3])).unwrap();
+        sender.send(Job::Commit(vec![0.0; 4])).unwrap();
+        sender.send(Job::Partial(vec![0.0; 5])).unwrap();
+        drop(sender);
+
+        let mut decoded = Vec::new();
+        dispatch(receiver, &|| false, &mut |job| {
+            decoded.push(match job {
+                Job::Partial(samples) => ("partial", samples.len()),
+                Job::Commit(samples) => ("commit", samples.len()),
+            })
+        });
+        assert_eq!(decoded, vec![("commit", 2), ("commit", 4), ("partial", 5)]);
+    }
+
+    #[test]
+    fn takes_every_sample_at_16k() {
+        let (jobs, _receiver) = mpsc::channel();
+        let mut session = StreamingSession::new("s".to_string(), 48_000, jobs);
+        session.vad = None;
+        let input: Vec<f32> = (0..48_000).map(|i| (i as f32 * 0.05).sin()).collect();
+        for chunk in input.chunks(4_096) {
+            session.push_chunk(chunk);
+        }
+        assert_eq!(
+            session.take_audio(),
+            crate::audio::resample(&input, 48_000, WHISPER_SAMPLE_RATE)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Custom vocabulary: terms primed through the whisper prompt, then corrected
+//! in the transcript by a replacement pass.
+
+use crate::db::VocabularyTerm;
+use std::cmp::Reverse;
+
+#[derive(Debug, Clone, Default)]
+pub struct Vocabulary {
+    /// Terms for the initial prompt.
+    terms: Vec<String>,
+    /// `(pattern, replacement)`, longest pattern first.
+    replacements: Vec<(String, String)>,
+}
+
+impl Vocabulary {
+    pub fn new(entries: Vec<VocabularyTerm>) -> Self {
+        let mut terms = Vec::with_capacity(entries.len());
+        let mut replacements = Vec::new();
+        for entry in entries {
+            let target = entry.replacement.unwrap_or_else(|| entry.term.clone());
+            for pattern in [Some(entry.term.clone()), entry.reading] {
+                if let Some(pattern) = pattern.filter(|p| !p.is_empty() && *p != target) {
+                    replacements.push((pattern, target.clone()));
+                }
+            }
+            terms.push(entry.term);
+        }
+        replacements.sort_by_key(|(pattern, _)| Reverse(pattern.len()));
+        Self {
+            terms,
+            replacements,
+        }
+    }
+
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    /// Replace every pattern occurrence in a single left-to-right pass, so
+    /// replaced text is never matched again. At each position the longest
+    /// pattern wins. A pattern starting or ending in a Latin letter or digit
+    /// only matches a whole word there, so `gh` leaves "high" alone; Japanese
+    /// has no spaces and matches anywhere.
+    pub fn apply(&self, text: &str) -> String {
+        if self.replacements.is_empty() {
+            return text.to_string();
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut prev = None;
+        while let Some(c) = rest.chars().next() {
+            match self.replacements.iter().find(|(pattern, _)| {
+                rest.strip_prefix(pattern.as_str())
+                    .is_some_and(|after| at_boundaries(pattern, prev, after.chars().next()))
+            }) {
+                Some((pattern, replacement)) => {
+                    out.push_str(replacement);
+                    rest = &rest[pattern.len()..];
+                    prev = pattern.chars().last();
+                }
+                None => {
+                    out.push(c);
+                    rest = &rest[c.len_utf8()..];
+                    prev = Some(c);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Whether `pattern`, found between `before` and `after`, does not cut into
+/// a Latin word on either side.
+fn at_boundaries(pattern: &str, before: Option<char>, after: Option<char>) -> bool {
+    let joins = |edge: Option<char>, neighbour: Option<char>| {
+        edge.is_some_and(is_latin_word_char) && neighbour.is_some_and(is_latin_word_char)
+    };
+    !joins(pattern.chars().next(), before) && !joins(pattern.chars().last(), after)
+}
+
+/// Letters and digits of the Latin script, up to Latin Extended-B.
+fn is_latin_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || (c.is_alphabetic() && ('\u{00C0}'..='\u{024F}').contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(entries: &[(&str, Option<&str>, Option<&str>)]) -> Vocabulary {
+        Vocabulary::new(
+            entries
+                .iter()
+                .enumerate()
+                .map(|(i, &(term, reading, replacement))| VocabularyTerm {
+                    id: i as i64,
+                    term: term.to_string(),
+                    reading: reading.map(str::to_string),
+                    replacement: replacement.map(str::to_string),
+                    created_at: 0,
+                    updated_at: 0,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn replaces_patterns() {
+        let vocabulary = vocabulary(&[
+            ("gh", None, Some("GitHub")),
+            ("Kotoba", Some("ことば"), None),
+            ("ことば遊び", None, Some("言葉遊び")),
+            ("C++", Some("シープラ"), None),
+            ("café", Some("cafe"), None),
+        ]);
+        let cases = [
+            ("push to gh now", "push to GitHub now"),
+            ("gh, then gh.", "GitHub, then GitHub."),
+            ("ghで公開", "GitHubで公開"),
+            ("high weight", "high weight"),
+            ("ghost sigh", "ghost sigh"),
+            ("gh2", "gh2"),
+            ("ことばを使う", "Kotobaを使う"),
+            ("日本語のことば", "日本語のKotoba"),
+            // The longest pattern wins.
+            ("ことば遊び", "言葉遊び"),
+            // Replaced text is not matched again.
+            ("GitHub gh", "GitHub GitHub"),
+            ("シープラで書く", "C++で書く"),
+            ("a cafe nearby", "a café nearby"),
+            ("cafeteria", "cafeteria"),
+            ("décafe", "décafe"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(vocabulary.apply(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn primes_every_term_and_skips_identity_patterns() {
+        let vocabulary = vocabulary(&[("Tauri", None, None), ("gh", None, Some("GitHub"))]);
+        assert_eq!(vocabulary.terms(), ["Tauri", "gh"]);
+        assert_eq!(vocabulary.replacements.len(), 1);
+        assert_eq!(vocabulary.apply("Tauri"), "Tauri");
+    }
+}
@@ -0,0 +1,154 @@
+//! Dedicated thread for full transcriptions. Commands submit jobs over a
+//! channel and await the reply, so whisper never runs on an async task and no
+//! state lock is held while it does.
+
+use crate::model_manager::ModelManager;
+use std::sync::mpsc;
+use tokio::sync::{mpsc as progress_channel, oneshot};
+
+struct Job {
+    task: Box<dyn FnOnce(&ModelManager) + Send>,
+    /// Whether the submitter has stopped waiting for the result.
+    abandoned: Box<dyn Fn() -> bool + Send>,
+}
+
+/// Handle to the worker thread; clones submit to the same queue.
+#[derive(Clone)]
+pub struct TranscriptionWorker {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl std::fmt::Debug for TranscriptionWorker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranscriptionWorker")
+            .finish_non_exhaustive()
+    }
+}
+
+impl TranscriptionWorker {
+    /// Start the worker thread, which owns `models` and exits once every
+    /// handle is dropped.
+    pub fn start(models: ModelManager) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("kotoba-transcriber".to_string())
+            .spawn(move || {
+                while let Ok(job) = receiver.recv() {
+                    if (job.abandoned)() {
+                        log::debug!("Skipping abandoned transcription job");
+                        continue;
+                    }
+                    (job.task)(&models);
+                }
+            })
+            .expect("failed to spawn transcription worker");
+        Self { jobs }
+    }
+
+    /// Queue `task` and wait for its result, passing progress reports to
+    /// `on_progress`. Jobs run one at a time in submission order; dropping
+    /// the returned future cancels a job that has not started yet.
+    pub async fn run<R: Send + 'static>(
+        &self,
+        task: impl FnOnce(&ModelManager, &mut dyn FnMut(u8)) -> Result<R, String> + Send + 'static,
+        on_progress: impl Fn(u8),
+    ) -> Result<R, String> {
+        let (reply, mut result) = oneshot::channel();
+        let (progress, mut progress_updates) = progress_channel::unbounded_channel();
+        // The progress receiver lives as long as the returned future.
+        let abandoned = {
+            let progress = progress.clone();
+            move || progress.is_closed()
+        };
+        let job = Job {
+            task: Box::new(move |models| {
+                let mut report = |percent| {
+                    let _ = progress.send(percent);
+                };
+                let _ = reply.send(task(models, &mut report));
+            }),
+            abandoned: Box::new(abandoned),
+        };
+        self.jobs
+            .send(job)
+            .map_err(|_| "Transcription worker stopped".to_string())?;
+
+        loop {
+            tokio::select! {
+                Some(percent) = progress_updates.recv() => on_progress(percent),
+                reply = &mut result => {
+                    // The task reported everything before replying.
+                    while let Ok(percent) = progress_updates.try_recv() {
+                        on_progress(percent);
+                    }
+                    return reply.map_err(|_| "Transcription worker stopped".to_string())?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn worker() -> TranscriptionWorker {
+        TranscriptionWorker::start(ModelManager::new(None))
+    }
+
+    #[tokio::test]
+    async fn delivers_every_progress_report() {
+        let worker = worker();
+        for _ in 0..20 {
+            let reports = RefCell::new(Vec::new());
+            let task = |_: &ModelManager, progress: &mut dyn FnMut(u8)| {
+                (0..=100).for_each(progress);
+                Ok(42)
+            };
+            let result = worker.run(task, |p| reports.borrow_mut().push(p)).await;
+            assert_eq!(result, Ok(42));
+            assert_eq!(reports.into_inner(), (0..=100).collect::<Vec<u8>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_a_job_whose_caller_stopped_waiting() {
+        let worker = worker();
+        let (started, started_rx) = oneshot::channel();
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        let busy = tokio::spawn({
+            let worker = worker.clone();
+            async move {
+                let task = move |_: &ModelManager, _: &mut dyn FnMut(u8)| {
+                    let _ = started.send(());
+                    let _ = gate.recv();
+                    Ok(())
+                };
+                worker.run(task, |_| {}).await
+            }
+        });
+        started_rx.await.unwrap();
+
+        // Queued behind the busy job, then given up on.
+        let ran = Arc::new(AtomicBool::new(false));
+        let task = {
+            let ran = ran.clone();
+            move |_: &ModelManager, _: &mut dyn FnMut(u8)| {
+                ran.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        };
+        let waited = tokio::time::timeout(Duration::from_millis(20), worker.run(task, |_| {}));
+        assert!(waited.await.is_err());
+
+        release.send(()).unwrap();
+        assert_eq!(busy.await.unwrap(), Ok(()));
+        let after = |_: &ModelManager, _: &mut dyn FnMut(u8)| Ok(());
+        assert_eq!(worker.run(after, |_| {}).await, Ok(()));
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,458 @@
+//! Dictation session lifecycle. Every state change goes through the
+//! transition table in [`RecordingState::next`], so a stale or repeated
+//! command from the renderer is rejected instead of corrupting the session.
+
+use crate::pipeline::cancel::CancelToken;
+use crate::pipeline::streaming::StreamingSession;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingState {
+    #[default]
+    Idle,
+    Recording,
+    Paused,
+    Processing,
+    /// The last session failed during processing. A new one may start.
+    Error,
+    /// The last session was cancelled. A new one may start.
+    Cancelled,
+}
+
+/// Something that happens to a session.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionEvent {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    /// Processing finished and the transcription was saved.
+    Complete,
+    /// Processing failed.
+    Fail,
+    Cancel,
+}
+
+impl RecordingState {
+    /// The state after `event`, or `None` when it is not allowed here.
+    pub fn next(self, event: SessionEvent) -> Option<Self> {
+        use RecordingState::*;
+        use SessionEvent::*;
+        match (self, event) {
+            (Idle | Error | Cancelled, Start) => Some(Recording),
+            (Recording, Pause) => Some(Paused),
+            (Paused, Resume) => Some(Recording),
+            (Recording | Paused, Stop) => Some(Processing),
+            (Processing, Complete) => Some(Idle),
+            (Processing, Fail) => Some(Error),
+            (Recording | Paused | Processing, Cancel) => Some(Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether a session is in progress.
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Recording | Self::Paused | Self::Processing)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionError {
+    IllegalTransition {
+        from: RecordingState,
+        event: SessionEvent,
+    },
+    /// A command named a session other than the active one.
+    SessionMismatch {
+        expected: Option<String>,
+        actual: String,
+    },
+    /// `finalize_session` was called again while the first call runs.
+    AlreadyFinalizing(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalTransition { from, event } => {
+                write!(f, "Cannot {event:?} a session while {from:?}")
+            }
+            Self::SessionMismatch {
+                expected: Some(expected),
+                actual,
+            } => write!(f, "Session {actual} is not the active session ({expected})"),
+            Self::SessionMismatch {
+                expected: None,
+                actual,
+            } => write!(f, "Session {actual} is not active"),
+            Self::AlreadyFinalizing(id) => write!(f, "Session {id} is already being finalized"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<SessionError> for String {
+    fn from(e: SessionError) -> Self {
+        e.to_string()
+    }
+}
+
+/// The dictation session in progress, if any.
+#[derive(Default)]
+pub struct SessionState {
+    pub recording_state: RecordingState,
+    pub active_session_id: Option<String>,
+    /// Live decoding for the active dictation session, fed by `process_audio_chunk`.
+    pub streaming: Option<StreamingSession>,
+    /// Fired by `cancel_session` to stop the active session's processing.
+    pub cancel: Option<CancelToken>,
+    /// When the active session recorded and when it was paused.
+    pub timeline: Option<SessionTimeline>,
+    /// Set once `finalize_session` has claimed the active session.
+    pub finalizing: bool,
+}
+
+/// A pause within a session, in milliseconds from its start.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PausedInterval {
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Wall-clock timing of a session, kept up to date by [`SessionState::apply`].
+#[derive(Debug, Clone)]
+pub struct SessionTimeline {
+    started_at: Instant,
+    paused_since: Option<Instant>,
+    stopped_at: Option<Instant>,
+    paused: Vec<PausedInterval>,
+}
+
+impl SessionTimeline {
+    fn new(now: Instant) -> Self {
+        Self {
+            started_at: now,
+            paused_since: None,
+            stopped_at: None,
+            paused: Vec::new(),
+        }
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.paused_since.get_or_insert(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        if let Some(since) = self.paused_since.take() {
+            self.paused.push(PausedInterval {
+                start_ms: self.offset_ms(since),
+                end_ms: self.offset_ms(now),
+            });
+        }
+    }
+
+    fn stop(&mut self, now: Instant) {
+        self.resume(now);
+        self.stopped_at = Some(now);
+    }
+
+    fn offset_ms(&self, at: Instant) -> u64 {
+        at.duration_since(self.started_at).as_millis() as u64
+    }
+
+    /// Finished pauses, in order.
+    pub fn paused_intervals(&self) -> &[PausedInterval] {
+        &self.paused
+    }
+
+    /// Time spent recording so far, excluding pauses.
+    pub fn speaking_duration(&self) -> Duration {
+        let end = self
+            .stopped_at
+            .or(self.paused_since)
+            .unwrap_or_else(Instant::now);
+        let paused_ms: u64 = self.paused.iter().map(|p| p.end_ms - p.start_ms).sum();
+        end.duration_since(self.started_at)
+            .saturating_sub(Duration::from_millis(paused_ms))
+    }
+}
+
+impl SessionState {
+    /// Apply `event`, leaving everything unchanged when it is illegal. Once
+    /// the session ends, its id, streaming worker, cancel token, timeline and
+    /// finalizing claim are dropped.
+    pub fn apply(&mut self, event: SessionEvent) -> Result<RecordingState, SessionError> {
+        self.apply_at(event, Instant::now())
+    }
+
+    fn apply_at(
+        &mut self,
+        event: SessionEvent,
+        now: Instant,
+    ) -> Result<RecordingState, SessionError> {
+        let next = self.check_transition(event)?;
+        self.recording_state = next;
+        if !next.is_active() {
+            self.active_session_id = None;
+            self.streaming = None;
+            self.cancel = None;
+            self.timeline = None;
+            self.finalizing = false;
+            return Ok(next);
+        }
+        match (event, &mut self.timeline) {
+            (SessionEvent::Start, timeline) => *timeline = Some(SessionTimeline::new(now)),
+            (SessionEvent::Pause, Some(timeline)) => timeline.pause(now),
+            (SessionEvent::Resume, Some(timeline)) => timeline.resume(now),
+            (SessionEvent::Stop, Some(timeline)) => timeline.stop(now),
+            _ => {}
+        }
+        Ok(next)
+    }
+
+    /// The state `event` would lead to, without applying it.
+    pub fn check_transition(&self, event: SessionEvent) -> Result<RecordingState, SessionError> {
+        self.recording_state
+            .next(event)
+            .ok_or(SessionError::IllegalTransition {
+                from: self.recording_state,
+                event,
+            })
+    }
+
+    /// Claim the stopped session `session_id` for finalizing, so a repeated
+    /// `finalize_session` is rejected while the first one runs.
+    pub fn claim_finalize(&mut self, session_id: &str) -> Result<(), SessionError> {
+        self.check_session(session_id)?;
+        self.check_transition(SessionEvent::Complete)?;
+        if self.finalizing {
+            return Err(SessionError::AlreadyFinalizing(session_id.to_string()));
+        }
+        self.finalizing = true;
+        Ok(())
+    }
+
+    /// Fail unless `session_id` is the active session.
+    pub fn check_session(&self, session_id: &str) -> Result<(), SessionError> {
+        if self.active_session_id.as_deref() == Some(session_id) {
+            Ok(())
+        } else {
+            Err(SessionError::SessionMismatch {
+                expected: self.active_session_id.clone(),
+                actual: session_id.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RecordingState::*;
+    use SessionEvent::*;
+
+    const STATES: [RecordingState; 6] = [Idle, Recording, Paused, Processing, Error, Cancelled];
+    const EVENTS: [SessionEvent; 7] = [Start, Pause, Resume, Stop, Complete, Fail, Cancel];
+
+    /// Every legal transition; anything else must be rejected.
+    const ALLOWED: [(RecordingState, SessionEvent, RecordingState); 12] = [
+        (Idle, Start, Recording),
+        (Error, Start, Recording),
+        (Cancelled, Start, Recording),
+        (Recording, Pause, Paused),
+        (Paused, Resume, Recording),
+        (Recording, Stop, Processing),
+        (Paused, Stop, Processing),
+        (Processing, Complete, Idle),
+        (Processing, Fail, Error),
+        (Recording, Cancel, Cancelled),
+        (Paused, Cancel, Cancelled),
+        (Processing, Cancel, Cancelled),
+    ];
+
+    fn session(state: RecordingState) -> SessionState {
+        SessionState {
+            recording_state: state,
+            active_session_id: state.is_active().then(|| "a".to_string()),
+            cancel: state.is_active().then(CancelToken::default),
+            timeline: state
+                .is_active()
+                .then(|| SessionTimeline::new(Instant::now())),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transition_table_is_exhaustive() {
+        for from in STATES {
+            for event in EVENTS {
+                let expected = ALLOWED
+                    .iter()
+                    .find(|(f, e, _)| *f == from && *e == event)
+                    .map(|(_, _, to)| *to);
+                assert_eq!(from.next(event), expected, "{from:?} + {event:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_transition_leaves_session_unchanged() {
+        for from in STATES {
+            for event in EVENTS {
+                if from.next(event).is_some() {
+                    continue;
+                }
+                let mut s = session(from);
+                assert_eq!(
+                    s.apply(event),
+                    Err(SessionError::IllegalTransition { from, event })
+                );
+                assert_eq!(s.recording_state, from);
+                assert_eq!(s.active_session_id.is_some(), from.is_active());
+                assert_eq!(s.cancel.is_some(), from.is_active());
+            }
+        }
+    }
+
+    #[test]
+    fn stop_twice_is_rejected() {
+        let mut s = session(Recording);
+        assert_eq!(s.apply(Stop), Ok(Processing));
+        assert_eq!(
+            s.apply(Stop),
+            Err(SessionError::IllegalTransition {
+                from: Processing,
+                event: Stop
+            })
+        );
+    }
+
+    #[test]
+    fn ending_a_session_clears_it() {
+        for (from, event) in [
+            (Processing, Complete),
+            (Processing, Fail),
+            (Recording, Cancel),
+        ] {
+            let mut s = session(from);
+            let to = s.apply(event).unwrap();
+            assert!(!to.is_active());
+            assert_eq!(s.active_session_id, None);
+            assert!(s.cancel.is_none());
+            assert!(s.timeline.is_none());
+        }
+    }
+
+    #[test]
+    fn continuing_a_session_keeps_it() {
+        let mut s = session(Recording);
+        s.apply(Stop).unwrap();
+        assert_eq!(s.active_session_id.as_deref(), Some("a"));
+        assert!(s.cancel.is_some());
+    }
+
+    #[test]
+    fn check_session_rejects_other_ids() {
+        let s = session(Processing);
+        assert_eq!(s.check_session("a"), Ok(()));
+        assert_eq!(
+            s.check_session("b"),
+            Err(SessionError::SessionMismatch {
+                expected: Some("a".to_string()),
+                actual: "b".to_string(),
+            })
+        );
+
+        let idle = session(Idle);
+        assert_eq!(
+            idle.check_session("a"),
+            Err(SessionError::SessionMismatch {
+                expected: None,
+                actual: "a".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn finalize_twice_is_rejected() {
+        let mut s = session(Recording);
+        assert_eq!(
+            s.claim_finalize("a"),
+            Err(SessionError::IllegalTransition {
+                from: Recording,
+                event: Complete
+            })
+        );
+        s.apply(Stop).unwrap();
+        assert!(matches!(
+            s.claim_finalize("b"),
+            Err(SessionError::SessionMismatch { .. })
+        ));
+        assert_eq!(s.claim_finalize("a"), Ok(()));
+        assert_eq!(
+            s.claim_finalize("a"),
+            Err(SessionError::AlreadyFinalizing("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn ending_a_session_releases_the_finalize_claim() {
+        for event in [Complete, Fail, Cancel] {
+            let mut s = session(Processing);
+            s.claim_finalize("a").unwrap();
+            s.apply(event).unwrap();
+            assert!(!s.finalizing);
+
+            s.apply(Start).unwrap();
+            s.active_session_id = Some("b".to_string());
+            s.apply(Stop).unwrap();
+            assert_eq!(s.claim_finalize("b"), Ok(()), "after {event:?}");
+        }
+    }
+
+    #[test]
+    fn pauses_are_excluded_from_speaking_time() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let mut s = SessionState::default();
+        s.apply_at(Start, at(0)).unwrap();
+        s.apply_at(Pause, at(1_000)).unwrap();
+        s.apply_at(Resume, at(3_000)).unwrap();
+        s.apply_at(Pause, at(4_000)).unwrap();
+        // Stopping while paused closes the open pause.
+        s.apply_at(Stop, at(4_500)).unwrap();
+
+        let timeline = s.timeline.as_ref().unwrap();
+        assert_eq!(
+            timeline.paused_intervals(),
+            [
+                PausedInterval {
+                    start_ms: 1_000,
+                    end_ms: 3_000
+                },
+                PausedInterval {
+                    start_ms: 4_000,
+                    end_ms: 4_500
+                },
+            ]
+        );
+        assert_eq!(timeline.speaking_duration(), Duration::from_millis(2_000));
+    }
+
+    #[test]
+    fn speaking_time_stops_while_paused() {
+        let t0 = Instant::now();
+        let mut s = SessionState::default();
+        s.apply_at(Start, t0).unwrap();
+        s.apply_at(Pause, t0 + Duration::from_millis(700)).unwrap();
+        let timeline = s.timeline.as_ref().unwrap();
+        assert!(timeline.paused_intervals().is_empty());
+        assert_eq!(timeline.speaking_duration(), Duration::from_millis(700));
+    }
+}
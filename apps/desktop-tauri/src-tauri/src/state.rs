@@ -1,12 +1,29 @@
 use crate::db::Database;
+use crate::model_manager::ModelManager;
+use crate::pipeline::cancel::CancelToken;
+use crate::pipeline::worker::TranscriptionWorker;
+use crate::session::SessionState;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
 
 /// In-memory application state shared across Tauri commands.
+///
+/// Each part has its own lock, so history queries and settings changes do not
+/// wait on an active dictation. When several are needed, take them in field
+/// order: session, then settings, then db. `file_jobs` is never held with
+/// another lock.
 pub struct AppState {
-    pub db: Database,
-    pub settings: AppSettingsData,
-    pub recording_state: RecordingState,
-    pub active_session_id: Option<String>,
+    session: Mutex<SessionState>,
+    settings: RwLock<AppSettingsData>,
+    db: Mutex<Database>,
+    /// Cancel tokens of running file transcription jobs, by job id.
+    file_jobs: Mutex<HashMap<String, CancelToken>>,
+    /// Loaded speech model, shared with transcription threads.
+    pub models: ModelManager,
+    /// Runs full transcriptions off the async runtime.
+    pub worker: TranscriptionWorker,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -22,6 +39,8 @@ pub struct AppSettingsData {
     pub preferences: Option<AppPreferences>,
     pub onboarding: Option<OnboardingSettings>,
     pub telemetry: Option<TelemetrySettings>,
+    pub text_normalization: Option<TextNormalizationSettings>,
+    pub inverse_text_normalization: Option<ItnSettings>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +64,14 @@ pub struct TranscriptionSettings {
     pub language: Option<String>,
     pub auto_transcribe: Option<bool>,
     pub preload_whisper_model: Option<bool>,
+    /// Maximum characters of recent context given to whisper as its initial
+    /// prompt; `0` disables it.
+    pub initial_prompt_length: Option<u32>,
+    /// Recent transcriptions the initial prompt draws on. Defaults to 10.
+    pub initial_prompt_history: Option<u32>,
+    /// Seconds an unused speech model stays loaded; `0` keeps it loaded.
+    /// Defaults to 300.
+    pub model_idle_timeout_secs: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +109,9 @@ pub struct OllamaConfig {
 pub struct DictationSettings {
     pub auto_detect_enabled: bool,
     pub selected_language: String,
+    /// Languages auto-detection may choose from, e.g. `["ja", "en"]`.
+    /// `None` or empty allows every language whisper knows.
+    pub auto_detect_languages: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,27 +138,223 @@ pub struct TelemetrySettings {
     pub enabled: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "lowercase")]
-pub enum RecordingState {
-    Idle,
-    Recording,
-    Processing,
+/// House-style normalization of the final text. Unset fields use the
+/// defaults noted on each.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextNormalizationSettings {
+    /// Defaults to `false`; nothing is rewritten until the user opts in.
+    pub enabled: Option<bool>,
+    /// Width of letters and digits. Defaults to half-width.
+    pub alphanumeric_width: Option<CharacterWidth>,
+    /// Defaults to keeping whatever whisper wrote.
+    pub punctuation_style: Option<PunctuationStyle>,
+    /// Convert half-width katakana (ｶﾀｶﾅ) to full-width. Defaults to `true`.
+    pub fix_halfwidth_katakana: Option<bool>,
+    /// Spaces between Japanese and Latin text. Defaults to keeping them as is.
+    pub latin_spacing: Option<LatinSpacing>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CharacterWidth {
+    Keep,
+    Halfwidth,
+    Fullwidth,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PunctuationStyle {
+    Keep,
+    /// 、。
+    Touten,
+    /// ，．
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LatinSpacing {
+    Keep,
+    /// "Rust の" style.
+    Insert,
+    /// "Rustの" style.
+    Remove,
+}
+
+/// Inverse text normalization: spoken Japanese numbers ("三千五百円") in
+/// written form ("3,500円"). Unset fields use the defaults noted on each.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItnSettings {
+    /// Defaults to `false`, so transcripts are not rewritten unasked.
+    pub enabled: Option<bool>,
+    /// "3,500円" rather than "3500円". Years are never grouped. Defaults to
+    /// `true`.
+    pub digit_grouping: Option<bool>,
+    /// Keep 万, 億 and 兆 ("1万2000円") instead of writing out every digit
+    /// ("12,000円"). Defaults to `true`.
+    pub keep_large_units: Option<bool>,
+    /// Defaults to `suffix`.
+    pub currency_style: Option<CurrencyStyle>,
+    /// Defaults to `halfwidth`.
+    pub percent_style: Option<PercentStyle>,
+    /// Defaults to `kanji`.
+    pub date_style: Option<DateStyle>,
+    /// Defaults to `kanji`.
+    pub time_style: Option<TimeStyle>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CurrencyStyle {
+    /// 3,500円, 5ドル
+    Suffix,
+    /// ¥3,500, $5
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PercentStyle {
+    /// 50%
+    Halfwidth,
+    /// 50％
+    Fullwidth,
+    /// 50パーセント
+    Katakana,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DateStyle {
+    /// 2024年3月5日
+    Kanji,
+    /// 2024/3/5
+    Slash,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeStyle {
+    /// 15時30分
+    Kanji,
+    /// 15:30
+    Colon,
 }
 
 impl AppState {
     pub fn new(db: Database) -> Self {
         let settings = db.load_settings().unwrap_or_default();
+        let models = ModelManager::new(
+            settings
+                .transcription
+                .as_ref()
+                .and_then(|t| t.model_idle_timeout_secs),
+        );
         Self {
-            db,
-            settings,
-            recording_state: RecordingState::Idle,
-            active_session_id: None,
+            session: Mutex::new(SessionState::default()),
+            settings: RwLock::new(settings),
+            db: Mutex::new(db),
+            file_jobs: Mutex::new(HashMap::new()),
+            worker: TranscriptionWorker::start(models.clone()),
+            models,
+        }
+    }
+
+    pub fn session(&self) -> Result<MutexGuard<'_, SessionState>, String> {
+        self.session.lock().map_err(|e| e.to_string())
+    }
+
+    pub fn settings(&self) -> Result<RwLockReadGuard<'_, AppSettingsData>, String> {
+        self.settings.read().map_err(|e| e.to_string())
+    }
+
+    pub fn db(&self) -> Result<MutexGuard<'_, Database>, String> {
+        self.db.lock().map_err(|e| e.to_string())
+    }
+
+    pub fn file_jobs(&self) -> Result<MutexGuard<'_, HashMap<String, CancelToken>>, String> {
+        self.file_jobs.lock().map_err(|e| e.to_string())
+    }
+
+    /// Change the settings and persist them.
+    pub fn update_settings<R>(
+        &self,
+        update: impl FnOnce(&mut AppSettingsData) -> R,
+    ) -> Result<R, String> {
+        let mut settings = self.settings.write().map_err(|e| e.to_string())?;
+        let result = update(&mut settings);
+        self.db()?
+            .save_settings(&settings)
+            .map_err(|e| e.to_string())?;
+        Ok(result)
+    }
+
+    /// Local file of the selected speech model, if it is downloaded.
+    pub fn speech_model_path(&self) -> Result<Option<String>, String> {
+        let model_id = self
+            .settings()?
+            .model_providers_config
+            .as_ref()
+            .and_then(|c| c.default_speech_model.clone());
+        match model_id {
+            Some(model_id) => self
+                .db()?
+                .get_model_local_path(&model_id)
+                .map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    /// Start loading the selected speech model when preloading is enabled.
+    pub fn preload_speech_model(&self) -> Result<(), String> {
+        let preload = self
+            .settings()?
+            .transcription
+            .as_ref()
+            .and_then(|t| t.preload_whisper_model)
+            .unwrap_or(false);
+        if preload {
+            if let Some(path) = self.speech_model_path()? {
+                self.models.preload(PathBuf::from(path));
+            }
+        }
+        Ok(())
+    }
+
+    /// After the file of model `removed_id` is gone, point
+    /// `default_speech_model` at the most recently downloaded speech model if
+    /// it was the selected one.
+    pub fn fall_back_speech_model(&self, removed_id: &str) -> Result<(), String> {
+        let selected = self
+            .settings()?
+            .model_providers_config
+            .as_ref()
+            .and_then(|c| c.default_speech_model.clone());
+        if selected.as_deref() != Some(removed_id) || self.speech_model_path()?.is_some() {
+            return Ok(());
         }
+        let fallback = self
+            .db()?
+            .get_models()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|m| m.model_type == "speech" && m.local_path.is_some())
+            .max_by_key(|m| m.downloaded_at)
+            .map(|m| m.id);
+        log::info!("Speech model {removed_id} was removed; falling back to {fallback:?}");
+        self.update_settings(|settings| {
+            if let Some(config) = settings.model_providers_config.as_mut() {
+                config.default_speech_model = fallback;
+            }
+        })?;
+        self.preload_speech_model()
     }
 
     /// Check if onboarding needs to be shown (no completed onboarding in settings).
-    pub fn needs_onboarding(&self) -> bool {
-        self.settings.onboarding.is_none()
+    pub fn needs_onboarding(&self) -> Result<bool, String> {
+        Ok(self.settings()?.onboarding.is_none())
     }
 }
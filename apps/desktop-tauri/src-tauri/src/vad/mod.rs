@@ -0,0 +1,264 @@
+//! Voice activity detection with the bundled Silero VAD v6 model.
+//!
+//! Per-frame speech probabilities come from [`SileroVad`]; [`speech_segments`]
+//! turns them into time ranges with the same hysteresis as the Electron
+//! `extractSpeechFromVad`, and [`trim_silence`] cuts the leading and trailing
+//! silence before audio reaches whisper.
+
+mod silero;
+
+pub use silero::SileroVad;
+
+use serde::Serialize;
+
+/// Sample rate the model expects.
+pub const VAD_SAMPLE_RATE: u32 = 16_000;
+
+/// Samples per frame: 32 ms at 16 kHz.
+pub const FRAME_SIZE: usize = 512;
+
+const FRAME_DURATION_MS: i64 = (FRAME_SIZE as i64 * 1000) / VAD_SAMPLE_RATE as i64;
+
+/// Hysteresis settings for turning frame probabilities into segments.
+#[derive(Debug, Clone, Copy)]
+pub struct VadConfig {
+    /// Probability needed to start a segment.
+    pub start_threshold: f32,
+    /// Probability below which a frame counts towards ending a segment.
+    pub end_threshold: f32,
+    /// Consecutive frames above `start_threshold` to confirm speech.
+    pub start_frames: usize,
+    /// Consecutive quiet frames that end a segment.
+    pub end_silence_frames: usize,
+    /// Frames kept before the confirmed start.
+    pub pre_roll_frames: usize,
+    /// Frames kept after the end.
+    pub post_roll_frames: usize,
+    /// Shorter segments are dropped.
+    pub min_speech_frames: usize,
+}
+
+impl Default for VadConfig {
+    /// The Electron app's "sensitive" preset, which picks up quieter speech.
+    fn default() -> Self {
+        Self {
+            start_threshold: 0.3,
+            end_threshold: 0.1,
+            start_frames: 2,
+            end_silence_frames: 20,
+            pre_roll_frames: 15,
+            post_roll_frames: 6,
+            min_speech_frames: 3,
+        }
+    }
+}
+
+/// A detected stretch of speech, in milliseconds from the start of the audio.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+impl SpeechSegment {
+    fn from_frames(start: usize, end_inclusive: usize) -> Self {
+        Self {
+            start_ms: start as i64 * FRAME_DURATION_MS,
+            end_ms: (end_inclusive as i64 + 1) * FRAME_DURATION_MS,
+        }
+    }
+}
+
+/// Run the model over 16 kHz mono samples and return one probability per
+/// frame. A trailing partial frame is zero-padded.
+pub fn frame_probabilities(samples: &[f32]) -> Result<Vec<f32>, String> {
+    let mut vad = SileroVad::new()?;
+    let mut frame = [0.0f32; FRAME_SIZE];
+    samples
+        .chunks(FRAME_SIZE)
+        .map(|chunk| {
+            frame[..chunk.len()].copy_from_slice(chunk);
+            frame[chunk.len()..].fill(0.0);
+            vad.process_frame(&frame)
+        })
+        .collect()
+}
+
+/// Detect speech in 16 kHz mono samples with the default configuration.
+pub fn detect(samples: &[f32]) -> Result<Vec<SpeechSegment>, String> {
+    let probs = frame_probabilities(samples)?;
+    Ok(speech_segments(&probs, &VadConfig::default()))
+}
+
+/// Group frame probabilities into merged speech segments.
+pub fn speech_segments(probs: &[f32], config: &VadConfig) -> Vec<SpeechSegment> {
+    let Some(last_frame) = probs.len().checked_sub(1) else {
+        return Vec::new();
+    };
+
+    let mut frames: Vec<(usize, usize)> = Vec::new();
+    let mut in_speech = false;
+    let mut speech_run = 0;
+    let mut silence_run = 0;
+    let mut start = 0;
+
+    let close = |start: usize, end: usize, frames: &mut Vec<(usize, usize)>| {
+        if end + 1 - start >= config.min_speech_frames {
+            frames.push((start, end));
+        }
+    };
+
+    for (i, &prob) in probs.iter().enumerate() {
+        if !in_speech {
+            if prob >= config.start_threshold {
+                speech_run += 1;
+                if speech_run >= config.start_frames {
+                    in_speech = true;
+                    start = (i + 1).saturating_sub(speech_run + config.pre_roll_frames);
+                    silence_run = 0;
+                }
+            } else {
+                speech_run = 0;
+            }
+        } else if prob < config.end_threshold {
+            silence_run += 1;
+            if silence_run >= config.end_silence_frames {
+                close(
+                    start,
+                    (i + config.post_roll_frames).min(last_frame),
+                    &mut frames,
+                );
+                in_speech = false;
+                speech_run = 0;
+                silence_run = 0;
+            }
+        } else {
+            silence_run = 0;
+        }
+    }
+    if in_speech {
+        close(start, last_frame, &mut frames);
+    }
+
+    // Pre-roll can reach back into the previous segment's post-roll.
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(frames.len());
+    for (start, end) in frames {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(start, end)| SpeechSegment::from_frames(start, end))
+        .collect()
+}
+
+/// Slice of `samples` from the start of the first segment to the end of the
+/// last, plus the offset in milliseconds of the returned slice.
+/// Silence between segments is kept so whisper sees natural pauses.
+pub fn trim_silence<'a>(samples: &'a [f32], segments: &[SpeechSegment]) -> (&'a [f32], i64) {
+    let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+        return (&samples[..0], 0);
+    };
+    let start = ms_to_samples(first.start_ms).min(samples.len());
+    let end = ms_to_samples(last.end_ms).clamp(start, samples.len());
+    (&samples[start..end], first.start_ms)
+}
+
+fn ms_to_samples(ms: i64) -> usize {
+    (ms.max(0) as usize) * VAD_SAMPLE_RATE as usize / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: VadConfig = VadConfig {
+        start_threshold: 0.5,
+        end_threshold: 0.2,
+        start_frames: 2,
+        end_silence_frames: 3,
+        pre_roll_frames: 1,
+        post_roll_frames: 1,
+        min_speech_frames: 3,
+    };
+
+    /// One frame per character: `#` speech, `-` between the thresholds,
+    /// `.` silence.
+    fn probs(frames: &str) -> Vec<f32> {
+        frames
+            .chars()
+            .map(|c| match c {
+                '#' => 0.9,
+                '-' => 0.3,
+                _ => 0.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_frames_into_segments() {
+        let cases: &[(&str, &str, usize, &[(usize, usize)])] = &[
+            ("no frames", "", 3, &[]),
+            ("silence", "........", 3, &[]),
+            ("isolated frames are not confirmed", "..#.#..#..", 3, &[]),
+            ("pre- and post-roll", "..##......", 3, &[(1, 7)]),
+            ("speech from the first frame", "##......", 3, &[(0, 5)]),
+            ("speech to the last frame", "....####", 3, &[(3, 7)]),
+            ("post-roll stops at the end", "..##...", 3, &[(1, 6)]),
+            (
+                "frames between thresholds hold a segment",
+                "##-.-.-...",
+                3,
+                &[(0, 9)],
+            ),
+            ("overlapping rolls merge", "##...##...", 3, &[(0, 9)]),
+            ("separate segments", "##.......##", 3, &[(0, 5), (8, 10)]),
+            ("short segments are dropped", "..##......", 8, &[]),
+        ];
+        for &(name, frames, min_speech_frames, expected) in cases {
+            let config = VadConfig {
+                min_speech_frames,
+                ..CONFIG
+            };
+            let expected: Vec<SpeechSegment> = expected
+                .iter()
+                .map(|&(start, end)| SpeechSegment::from_frames(start, end))
+                .collect();
+            assert_eq!(speech_segments(&probs(frames), &config), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn segments_are_in_milliseconds() {
+        let segments = speech_segments(&probs("..##......"), &CONFIG);
+        assert_eq!(
+            segments,
+            vec![SpeechSegment {
+                start_ms: 32,
+                end_ms: 256
+            }]
+        );
+    }
+
+    #[test]
+    fn trims_leading_and_trailing_silence() {
+        let samples: Vec<f32> = (0..VAD_SAMPLE_RATE).map(|i| i as f32).collect();
+        let segment = |start_ms, end_ms| SpeechSegment { start_ms, end_ms };
+
+        let (trimmed, offset) = trim_silence(&samples, &[segment(100, 300), segment(500, 700)]);
+        assert_eq!(offset, 100);
+        assert_eq!(trimmed, &samples[1_600..11_200]);
+
+        // Segments past the end of the audio are clamped.
+        let (trimmed, offset) = trim_silence(&samples, &[segment(900, 1_500)]);
+        assert_eq!(offset, 900);
+        assert_eq!(trimmed, &samples[14_400..]);
+
+        let (trimmed, offset) = trim_silence(&samples, &[]);
+        assert!(trimmed.is_empty());
+        assert_eq!(offset, 0);
+    }
+}
@@ -0,0 +1,309 @@
+//! Silero VAD v6 on tract, a pure-Rust ONNX runtime (CPU only).
+//!
+//! The exported graph wraps the 8 kHz and 16 kHz networks, and several
+//! shape-dependent steps inside them, in ONNX `If` nodes. tract's own `If`
+//! types both branches and requires them to agree, which this graph does not
+//! satisfy. Since every condition is fixed once the input shapes are known, the
+//! `If` operator is replaced at parse time with [`StaticIf`], which inlines the
+//! branch taken.
+
+use super::{FRAME_SIZE, VAD_SAMPLE_RATE};
+use std::sync::{Arc, OnceLock};
+use tract_onnx::model::{ParseResult, ParsingContext};
+use tract_onnx::pb::NodeProto;
+use tract_onnx::prelude::*;
+use tract_onnx::tract_hir::internal::*;
+
+static MODEL_BYTES: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../desktop/models/silero_vad_v6.onnx"
+));
+
+/// v6 prepends the last 64 samples of the previous frame to each input.
+const CONTEXT_SIZE: usize = 64;
+const STATE_SHAPE: [usize; 3] = [2, 1, 128];
+
+type Plan = TypedRunnableModel<TypedModel>;
+
+/// Optimising the graph takes a noticeable moment, so it happens once per
+/// process and the plan is shared; recurrent state lives in each `SileroVad`.
+static PLAN: OnceLock<Result<Arc<Plan>, String>> = OnceLock::new();
+
+fn plan() -> Result<Arc<Plan>, String> {
+    PLAN.get_or_init(|| {
+        load()
+            .map(Arc::new)
+            .map_err(|e| format!("Failed to load VAD model: {e}"))
+    })
+    .clone()
+}
+
+fn load() -> TractResult<Plan> {
+    let mut onnx = tract_onnx::onnx().with_ignore_output_shapes(true);
+    onnx.op_register.insert("If", static_if);
+    onnx.model_for_read(&mut &*MODEL_BYTES)?
+        .with_input_fact(0, f32::fact([1, CONTEXT_SIZE + FRAME_SIZE]).into())?
+        .with_input_fact(1, f32::fact(STATE_SHAPE).into())?
+        .with_input_fact(2, tensor0(VAD_SAMPLE_RATE as i64).into())?
+        .into_optimized()?
+        .into_runnable()
+}
+
+/// Streaming detector for one audio stream at 16 kHz.
+pub struct SileroVad {
+    plan: Arc<Plan>,
+    state: Tensor,
+    context: [f32; CONTEXT_SIZE],
+}
+
+impl SileroVad {
+    pub fn new() -> Result<Self, String> {
+        Ok(Self {
+            plan: plan()?,
+            state: Tensor::zero::<f32>(&STATE_SHAPE).map_err(|e| e.to_string())?,
+            context: [0.0; CONTEXT_SIZE],
+        })
+    }
+
+    /// Speech probability in `0.0..=1.0` for the next `FRAME_SIZE` samples.
+    pub fn process_frame(&mut self, frame: &[f32]) -> Result<f32, String> {
+        if frame.len() != FRAME_SIZE {
+            return Err(format!(
+                "VAD frame must be {FRAME_SIZE} samples, got {}",
+                frame.len()
+            ));
+        }
+        let mut input = Vec::with_capacity(CONTEXT_SIZE + FRAME_SIZE);
+        input.extend_from_slice(&self.context);
+        input.extend_from_slice(frame);
+        self.context
+            .copy_from_slice(&input[input.len() - CONTEXT_SIZE..]);
+
+        let input = tract_ndarray::Array2::from_shape_vec((1, input.len()), input)
+            .map_err(|e| e.to_string())?
+            .into_tensor();
+        let outputs = self
+            .plan
+            .run(tvec!(
+                input.into(),
+                self.state.clone().into(),
+                tensor0(VAD_SAMPLE_RATE as i64).into()
+            ))
+            .map_err(|e| e.to_string())?;
+
+        let prob = outputs[0]
+            .as_slice::<f32>()
+            .map_err(|e| e.to_string())?
+            .first()
+            .copied()
+            .unwrap_or(0.0);
+        self.state = outputs[1].clone().into_tensor();
+        Ok(prob)
+    }
+}
+
+/// ONNX `If` whose condition must be known at analysis time.
+#[derive(Debug, Clone)]
+struct StaticIf {
+    then_body: InferenceModel,
+    then_input_mapping: Vec<usize>,
+    else_body: InferenceModel,
+    else_input_mapping: Vec<usize>,
+}
+
+/// Parse an `If` node the way tract's builder does. Values a branch reads from
+/// the enclosing graph become extra op inputs after the condition.
+fn static_if(
+    ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let ParseResult {
+        model: then_body,
+        unresolved_inputs: then_inputs,
+        ..
+    } = ctx.parse_graph(node.get_attr("then_branch")?)?;
+    let ParseResult {
+        model: else_body,
+        unresolved_inputs: else_inputs,
+        ..
+    } = ctx.parse_graph(node.get_attr("else_branch")?)?;
+
+    let mut inputs: Vec<String> = then_inputs.iter().chain(&else_inputs).cloned().collect();
+    inputs.sort();
+    inputs.dedup();
+    let mapping = |names: &[String]| -> Vec<usize> {
+        names
+            .iter()
+            .filter_map(|name| inputs.iter().position(|i| i == name))
+            .map(|ix| ix + 1)
+            .collect()
+    };
+
+    let op = StaticIf {
+        then_input_mapping: mapping(&then_inputs),
+        else_input_mapping: mapping(&else_inputs),
+        then_body,
+        else_body,
+    };
+    Ok((Box::new(op), inputs))
+}
+
+impl StaticIf {
+    fn branch(&self, cond: bool) -> (&InferenceModel, &[usize]) {
+        if cond {
+            (&self.then_body, &self.then_input_mapping)
+        } else {
+            (&self.else_body, &self.else_input_mapping)
+        }
+    }
+
+    fn branch_mut(&mut self, cond: bool) -> (&mut InferenceModel, &[usize]) {
+        if cond {
+            (&mut self.then_body, &self.then_input_mapping)
+        } else {
+            (&mut self.else_body, &self.else_input_mapping)
+        }
+    }
+}
+
+impl Op for StaticIf {
+    fn name(&self) -> Cow<'_, str> {
+        "StaticIf".into()
+    }
+
+    not_a_typed_op!();
+}
+
+impl EvalOp for StaticIf {
+    fn is_stateless(&self) -> bool {
+        true
+    }
+
+    fn eval(&self, inputs: TVec<TValue>) -> TractResult<TVec<TValue>> {
+        let (body, mapping) = self.branch(inputs[0].cast_to_scalar::<bool>()?);
+        let inputs = mapping.iter().map(|&ix| inputs[ix].clone()).collect();
+        body.clone().into_runnable()?.run(inputs)
+    }
+}
+
+impl InferenceOp for StaticIf {
+    fn infer_facts(
+        &mut self,
+        inputs: TVec<&InferenceFact>,
+        outputs: TVec<&InferenceFact>,
+        observed: TVec<&InferenceFact>,
+    ) -> TractResult<(
+        TVec<InferenceFact>,
+        TVec<InferenceFact>,
+        TVec<InferenceFact>,
+    )> {
+        let mut inputs: TVec<InferenceFact> = inputs.into_iter().cloned().collect();
+        let mut outputs: TVec<InferenceFact> = outputs.into_iter().cloned().collect();
+        let observed = observed.into_iter().cloned().collect();
+
+        // Only the branch taken is analysed; the other may not even type-check
+        // for these input shapes.
+        let Some(cond) = inputs[0].value.concretize() else {
+            return Ok((inputs, outputs, observed));
+        };
+        let (body, mapping) = self.branch_mut(cond.cast_to_scalar::<bool>()?);
+        loop {
+            let mut changed = false;
+            for (body_ix, &outer_ix) in mapping.iter().enumerate() {
+                changed |= body
+                    .input_fact_mut(body_ix)?
+                    .unify_with_mut(&mut inputs[outer_ix])?;
+            }
+            for (ix, output) in outputs.iter_mut().enumerate() {
+                changed |= body.output_fact_mut(ix)?.unify_with_mut(output)?;
+            }
+            changed |= body.analyse(false)?;
+            if !changed {
+                return Ok((inputs, outputs, observed));
+            }
+        }
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.then_body.outputs.len())
+    }
+
+    fn to_typed(
+        &self,
+        source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let cond = source
+            .outlet_fact(node.inputs[0])?
+            .value
+            .concretize()
+            .with_context(|| format!("Condition of {} is not constant", node.name))?;
+        let (body, body_mapping) = self.branch(cond.cast_to_scalar::<bool>()?);
+        let body = body.clone().into_typed()?;
+
+        // Splice the branch into the parent graph in place of the op.
+        let mut wires: HashMap<OutletId, OutletId> = HashMap::new();
+        for (body_ix, input) in body.input_outlets()?.iter().enumerate() {
+            wires.insert(*input, mapping[&node.inputs[body_mapping[body_ix]]]);
+        }
+        for id in body.eval_order()? {
+            if wires.contains_key(&OutletId::new(id, 0)) {
+                continue;
+            }
+            let inner = body.node(id);
+            let inputs: TVec<OutletId> = inner.inputs.iter().map(|i| wires[i]).collect();
+            let outlets = target.wire_node(
+                format!("{}.{}", node.name, inner.name),
+                inner.op.clone(),
+                &inputs,
+            )?;
+            for (ix, outlet) in outlets.into_iter().enumerate() {
+                wires.insert(OutletId::new(id, ix), outlet);
+            }
+        }
+        body.output_outlets()?
+            .iter()
+            .map(|o| Ok(wires[o]))
+            .collect()
+    }
+
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn probabilities(samples: &[f32]) -> Vec<f32> {
+        let mut vad = SileroVad::new().unwrap();
+        samples
+            .chunks_exact(FRAME_SIZE)
+            .map(|frame| vad.process_frame(frame).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn scores_silence_low_and_speech_high() {
+        let silence = probabilities(&[0.0; VAD_SAMPLE_RATE as usize]);
+        assert!(silence.iter().all(|&p| p < 0.05), "{silence:?}");
+
+        let jfk = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../../packages/whisper-wrapper/whisper.cpp/samples/jfk.mp3");
+        let speech = crate::audio::load_mono(&jfk, VAD_SAMPLE_RATE).unwrap();
+        let probs = probabilities(&speech);
+        let voiced = probs.iter().filter(|&&p| p > 0.9).count();
+        // The clip is mostly speech, with a few pauses between phrases.
+        assert!(voiced * 2 > probs.len(), "{voiced} of {}", probs.len());
+        assert!(probs[..3].iter().all(|&p| p < 0.1), "{probs:?}");
+    }
+
+    #[test]
+    fn rejects_frames_of_the_wrong_size() {
+        let mut vad = SileroVad::new().unwrap();
+        assert!(vad.process_frame(&[0.0; FRAME_SIZE - 1]).is_err());
+        assert!(vad.process_frame(&[0.0; FRAME_SIZE]).is_ok());
+    }
+}
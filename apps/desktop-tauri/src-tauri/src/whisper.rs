@@ -0,0 +1,344 @@
+//! Safe wrapper around the vendored whisper.cpp (see `build.rs` and `native/`).
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_float, c_int, c_void};
+use std::path::Path;
+use std::ptr::NonNull;
+
+/// Sample rate whisper.cpp expects for its input PCM.
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+mod ffi {
+    use super::*;
+
+    #[repr(C)]
+    pub struct WhisperContext {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    pub struct KotobaFullOptions {
+        pub n_threads: i32,
+        pub language: *const c_char,
+        pub initial_prompt: *const c_char,
+        pub progress: Option<extern "C" fn(percent: i32, user_data: *mut c_void)>,
+        pub user_data: *mut c_void,
+        pub abort: Option<extern "C" fn(abort_data: *mut c_void) -> i32>,
+        pub abort_data: *mut c_void,
+    }
+
+    extern "C" {
+        pub fn kotoba_whisper_init(model_path: *const c_char) -> *mut WhisperContext;
+        pub fn kotoba_whisper_full(
+            ctx: *mut WhisperContext,
+            options: *const KotobaFullOptions,
+            samples: *const c_float,
+            n_samples: i32,
+        ) -> i32;
+
+        pub fn whisper_free(ctx: *mut WhisperContext);
+        pub fn whisper_full_n_segments(ctx: *mut WhisperContext) -> c_int;
+        pub fn whisper_full_get_segment_text(
+            ctx: *mut WhisperContext,
+            i_segment: c_int,
+        ) -> *const c_char;
+        pub fn whisper_full_get_segment_t0(ctx: *mut WhisperContext, i_segment: c_int) -> i64;
+        pub fn whisper_full_get_segment_t1(ctx: *mut WhisperContext, i_segment: c_int) -> i64;
+        pub fn whisper_full_get_segment_no_speech_prob(
+            ctx: *mut WhisperContext,
+            i_segment: c_int,
+        ) -> c_float;
+        pub fn whisper_full_n_tokens(ctx: *mut WhisperContext, i_segment: c_int) -> c_int;
+        pub fn whisper_full_get_token_id(
+            ctx: *mut WhisperContext,
+            i_segment: c_int,
+            i_token: c_int,
+        ) -> i32;
+        pub fn whisper_full_get_token_text(
+            ctx: *mut WhisperContext,
+            i_segment: c_int,
+            i_token: c_int,
+        ) -> *const c_char;
+        pub fn whisper_full_get_token_p(
+            ctx: *mut WhisperContext,
+            i_segment: c_int,
+            i_token: c_int,
+        ) -> c_float;
+        pub fn whisper_token_eot(ctx: *mut WhisperContext) -> i32;
+
+        pub fn whisper_pcm_to_mel(
+            ctx: *mut WhisperContext,
+            samples: *const c_float,
+            n_samples: c_int,
+            n_threads: c_int,
+        ) -> c_int;
+        pub fn whisper_lang_auto_detect(
+            ctx: *mut WhisperContext,
+            offset_ms: c_int,
+            n_threads: c_int,
+            lang_probs: *mut c_float,
+        ) -> c_int;
+        pub fn whisper_lang_max_id() -> c_int;
+        pub fn whisper_lang_id(lang: *const c_char) -> c_int;
+        pub fn whisper_lang_str(id: c_int) -> *const c_char;
+    }
+}
+
+/// Progress callback handed to the shim; `user_data` is the
+/// `&mut dyn FnMut(u8)` borrowed by `transcribe_with_callbacks`.
+extern "C" fn forward_progress(percent: i32, user_data: *mut c_void) {
+    // SAFETY: the callback outlives the `kotoba_whisper_full` call that
+    // invokes this, and whisper reports progress from the calling thread.
+    let on_progress = unsafe { &mut *(user_data as *mut &mut dyn FnMut(u8)) };
+    on_progress(percent.clamp(0, 100) as u8);
+}
+
+/// Abort callback handed to the shim; `abort_data` is the
+/// `&(dyn Fn() -> bool + Sync)` borrowed by `transcribe_with_callbacks`.
+extern "C" fn forward_abort(abort_data: *mut c_void) -> i32 {
+    // SAFETY: as for `forward_progress`; the callback is `Sync` because ggml
+    // may poll it from its compute threads.
+    let should_abort = unsafe { &*(abort_data as *const &(dyn Fn() -> bool + Sync)) };
+    i32::from(should_abort())
+}
+
+/// Options for a single `WhisperContext::transcribe` call.
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions {
+    /// ISO 639-1 code, or `None` to let whisper auto-detect.
+    pub language: Option<String>,
+    pub initial_prompt: Option<String>,
+    /// Number of CPU threads; `0` picks a default from the host.
+    pub n_threads: i32,
+}
+
+/// Result of language identification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// ISO 639-1 code, e.g. `"ja"`.
+    pub code: String,
+    pub probability: f32,
+}
+
+/// One decoded text segment with its timing in milliseconds.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub no_speech_prob: f32,
+    /// Mean log-probability of the segment's text tokens.
+    pub avg_logprob: f32,
+    /// Text tokens in order; their texts concatenate to `text`.
+    pub tokens: Vec<Token>,
+}
+
+/// One decoded text token. Byte-level BPE can split a multi-byte character
+/// across tokens, so only the byte length is kept.
+#[derive(Debug, Clone, Copy)]
+pub struct Token {
+    /// Length in bytes of this token's share of `Segment::text`.
+    pub len: usize,
+    /// Decoder probability of the token.
+    pub p: f32,
+}
+
+/// A loaded whisper model. Inference requires `&mut self`, so a context is
+/// never used from two threads at once.
+pub struct WhisperContext {
+    ctx: NonNull<ffi::WhisperContext>,
+}
+
+// SAFETY: whisper_context owns its buffers and has no thread affinity; all
+// access goes through `&mut self`.
+unsafe impl Send for WhisperContext {}
+
+impl WhisperContext {
+    /// Load a ggml model file for CPU inference.
+    pub fn new(model_path: &Path) -> Result<Self, String> {
+        let path = model_path
+            .to_str()
+            .ok_or_else(|| format!("Invalid model path: {}", model_path.display()))?;
+        let c_path = CString::new(path).map_err(|e| e.to_string())?;
+        let ptr = unsafe { ffi::kotoba_whisper_init(c_path.as_ptr()) };
+        NonNull::new(ptr)
+            .map(|ctx| Self { ctx })
+            .ok_or_else(|| format!("Failed to load whisper model: {}", model_path.display()))
+    }
+
+    /// Run full transcription over 16 kHz mono samples, calling
+    /// `on_progress` with the percentage decoded so far and stopping early
+    /// once `should_abort` returns `true`.
+    pub fn transcribe_with_callbacks(
+        &mut self,
+        samples: &[f32],
+        options: &TranscribeOptions,
+        mut on_progress: &mut dyn FnMut(u8),
+        should_abort: &(dyn Fn() -> bool + Sync),
+    ) -> Result<Vec<Segment>, String> {
+        if samples.is_empty() {
+            return Ok(Vec::new());
+        }
+        let n_samples =
+            i32::try_from(samples.len()).map_err(|_| "Audio is too long".to_string())?;
+
+        let language = options
+            .language
+            .as_deref()
+            .map(CString::new)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let initial_prompt = options
+            .initial_prompt
+            .as_deref()
+            .map(CString::new)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+
+        let n_threads = if options.n_threads > 0 {
+            options.n_threads
+        } else {
+            default_thread_count()
+        };
+
+        let raw_options = ffi::KotobaFullOptions {
+            n_threads,
+            language: language.as_ref().map_or(std::ptr::null(), |s| s.as_ptr()),
+            initial_prompt: initial_prompt
+                .as_ref()
+                .map_or(std::ptr::null(), |s| s.as_ptr()),
+            progress: Some(forward_progress),
+            user_data: &mut on_progress as *mut &mut dyn FnMut(u8) as *mut c_void,
+            abort: Some(forward_abort),
+            abort_data: &should_abort as *const &(dyn Fn() -> bool + Sync) as *mut c_void,
+        };
+
+        let ret = unsafe {
+            ffi::kotoba_whisper_full(self.ctx.as_ptr(), &raw_options, samples.as_ptr(), n_samples)
+        };
+        if ret != 0 && should_abort() {
+            return Err("Transcription aborted".to_string());
+        }
+        if ret != 0 {
+            return Err(format!("whisper_full failed with code {ret}"));
+        }
+
+        Ok(self.collect_segments())
+    }
+
+    /// Identify the spoken language from the first 30 s of 16 kHz mono
+    /// samples. With a non-empty `allowed` list, only those language codes
+    /// are considered.
+    pub fn detect_language(
+        &mut self,
+        samples: &[f32],
+        allowed: &[String],
+    ) -> Result<DetectedLanguage, String> {
+        let n_samples =
+            i32::try_from(samples.len()).map_err(|_| "Audio is too long".to_string())?;
+        let n_threads = default_thread_count();
+        let ctx = self.ctx.as_ptr();
+
+        let ret = unsafe { ffi::whisper_pcm_to_mel(ctx, samples.as_ptr(), n_samples, n_threads) };
+        if ret != 0 {
+            return Err(format!("whisper_pcm_to_mel failed with code {ret}"));
+        }
+        let n_langs = unsafe { ffi::whisper_lang_max_id() } + 1;
+        let mut probs = vec![0.0f32; n_langs.max(0) as usize];
+        let ret = unsafe { ffi::whisper_lang_auto_detect(ctx, 0, n_threads, probs.as_mut_ptr()) };
+        if ret < 0 {
+            return Err(format!("Language detection failed with code {ret}"));
+        }
+
+        let candidates: Vec<usize> = if allowed.is_empty() {
+            (0..probs.len()).collect()
+        } else {
+            allowed
+                .iter()
+                .filter_map(|code| CString::new(code.as_str()).ok())
+                .map(|code| unsafe { ffi::whisper_lang_id(code.as_ptr()) })
+                .filter_map(|id| usize::try_from(id).ok())
+                .filter(|&id| id < probs.len())
+                .collect()
+        };
+        let id = candidates
+            .into_iter()
+            .max_by(|&a, &b| probs[a].total_cmp(&probs[b]))
+            .ok_or_else(|| format!("No known language among {allowed:?}"))?;
+
+        Ok(DetectedLanguage {
+            code: unsafe { c_str_lossy(ffi::whisper_lang_str(id as c_int)) },
+            probability: probs[id],
+        })
+    }
+
+    fn collect_segments(&mut self) -> Vec<Segment> {
+        let ctx = self.ctx.as_ptr();
+        let n = unsafe { ffi::whisper_full_n_segments(ctx) };
+        (0..n)
+            .map(|i| unsafe {
+                let text = c_str_lossy(ffi::whisper_full_get_segment_text(ctx, i));
+                let tokens = Self::collect_tokens(ctx, i);
+                let avg_logprob = if tokens.is_empty() {
+                    0.0
+                } else {
+                    tokens
+                        .iter()
+                        .map(|t| t.p.max(f32::MIN_POSITIVE).ln())
+                        .sum::<f32>()
+                        / tokens.len() as f32
+                };
+                // whisper.cpp reports segment times in 10 ms units.
+                Segment {
+                    start_ms: ffi::whisper_full_get_segment_t0(ctx, i) * 10,
+                    end_ms: ffi::whisper_full_get_segment_t1(ctx, i) * 10,
+                    text,
+                    no_speech_prob: ffi::whisper_full_get_segment_no_speech_prob(ctx, i),
+                    avg_logprob,
+                    tokens,
+                }
+            })
+            .collect()
+    }
+
+    /// Timestamps, language tags and other special tokens have ids at or above
+    /// EOT and are left out.
+    unsafe fn collect_tokens(ctx: *mut ffi::WhisperContext, i_segment: c_int) -> Vec<Token> {
+        let eot = ffi::whisper_token_eot(ctx);
+        (0..ffi::whisper_full_n_tokens(ctx, i_segment))
+            .filter(|&j| ffi::whisper_full_get_token_id(ctx, i_segment, j) < eot)
+            .map(|j| {
+                let text = ffi::whisper_full_get_token_text(ctx, i_segment, j);
+                Token {
+                    len: if text.is_null() {
+                        0
+                    } else {
+                        CStr::from_ptr(text).to_bytes().len()
+                    },
+                    p: ffi::whisper_full_get_token_p(ctx, i_segment, j),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drop for WhisperContext {
+    fn drop(&mut self) {
+        unsafe { ffi::whisper_free(self.ctx.as_ptr()) };
+    }
+}
+
+unsafe fn c_str_lossy(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+/// Use the available cores, capped so the UI stays responsive.
+fn default_thread_count() -> i32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get().min(8) as i32)
+        .unwrap_or(4)
+}
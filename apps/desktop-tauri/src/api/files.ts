@@ -0,0 +1,56 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { Transcription } from "./transcriptions";
+
+export type FileTranscriptionStage =
+  | "queued"
+  | "decoding"
+  | "transcribing"
+  | "formatting"
+  | "saving"
+  | "completed"
+  | "failed"
+  | "cancelled";
+
+export interface FileTranscriptionProgress {
+  jobId: string;
+  filePath: string;
+  fileIndex: number;
+  fileCount: number;
+  stage: FileTranscriptionStage;
+  /** Percentage of the stage done, when known. */
+  percent?: number;
+  transcriptionId?: number;
+  error?: string;
+}
+
+export interface FileTranscriptionOutcome {
+  filePath: string;
+  transcription?: Transcription;
+  error?: string;
+}
+
+export const filesApi = {
+  getSupportedAudioExtensions: () =>
+    invoke<string[]>("get_supported_audio_extensions"),
+
+  transcribeFile: (path: string) =>
+    invoke<Transcription>("transcribe_file", { path }),
+
+  transcribeFiles: (paths: string[]) =>
+    invoke<FileTranscriptionOutcome[]>("transcribe_files", { paths }),
+
+  /** Stop a job; its id arrives with the `file-transcription-progress` events. */
+  cancelFileTranscription: (jobId: string) =>
+    invoke<void>("cancel_file_transcription", { jobId }),
+
+  onProgress: (
+    callback: (progress: FileTranscriptionProgress) => void,
+  ): Promise<UnlistenFn> =>
+    listen<FileTranscriptionProgress>(
+      "file-transcription-progress",
+      (event) => {
+        callback(event.payload);
+      },
+    ),
+};
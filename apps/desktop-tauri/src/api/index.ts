@@ -5,6 +5,9 @@
  */
 export * from "./settings";
 export * from "./transcriptions";
+export * from "./vocabulary";
+export * from "./textRules";
 export * from "./recording";
+export * from "./files";
 export * from "./models";
 export * from "./app";
@@ -1,4 +1,8 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+/** Outcome of the last integrity check of a model file. */
+export type ModelStatus = "verified" | "corrupt";
 
 export interface Model {
   id: string;
@@ -14,10 +18,62 @@ export interface Model {
   downloadedAt?: number;
   speed?: number;
   accuracy?: number;
+  /** Unset until the file has been checked. */
+  status?: ModelStatus;
+  verifiedAt?: number;
   createdAt: number;
   updatedAt: number;
 }
 
+/** Payload of the `model-loading` and `model-ready` events. */
+export interface ModelEvent {
+  modelPath: string;
+}
+
+export interface DownloadModelOptions {
+  id: string;
+  provider: string;
+  /** The remaining fields default to the built-in catalog entry for `id`. */
+  name?: string;
+  url?: string;
+  /** Expected hex SHA-1 of the file. */
+  checksum?: string;
+}
+
+/** Payload of the `model-download-progress` event. */
+export interface ModelDownloadProgress {
+  modelId: string;
+  provider: string;
+  downloadedBytes: number;
+  totalBytes?: number;
+}
+
+export interface MissingModelFile {
+  id: string;
+  provider: string;
+  localPath: string;
+}
+
+/** Result of `scan_model_files`, also the `model-files-report` payload. */
+export interface ModelFilesReport {
+  orphanFiles: string[];
+  missingFiles: MissingModelFile[];
+}
+
+/** Payload of the `model-corrupt` event. */
+export interface CorruptModel {
+  modelId: string;
+  provider: string;
+  localPath: string;
+  reason: string;
+}
+
+export interface ModelVerificationReport {
+  /** Number of files hashed. */
+  checked: number;
+  corrupt: CorruptModel[];
+}
+
 export const modelsApi = {
   getModels: () => invoke<Model[]>("get_models"),
 
@@ -28,6 +84,61 @@ export const modelsApi = {
 
   saveModel: (model: Model) => invoke<void>("save_model", { model }),
 
+  /** Also deletes the model file if it lives in the models directory. */
   deleteModel: (id: string, provider: string) =>
     invoke<void>("delete_model", { id, provider }),
+
+  scanModelFiles: () => invoke<ModelFilesReport>("scan_model_files"),
+
+  /** Delete orphaned files and forget models whose file is gone. */
+  reconcileModelFiles: () =>
+    invoke<ModelFilesReport>("reconcile_model_files"),
+
+  /** Resumes an interrupted download of the same model when retried. */
+  downloadModel: (options: DownloadModelOptions) =>
+    invoke<Model>("download_model", { options }),
+
+  /** Import a whisper model in ggml format from a local file. */
+  importModel: (path: string, name?: string) =>
+    invoke<Model>("import_model", { path, name: name ?? null }),
+
+  /** Re-hash every downloaded model against its recorded checksum. */
+  verifyModels: () => invoke<ModelVerificationReport>("verify_models"),
+
+  onDownloadProgress: (
+    callback: (progress: ModelDownloadProgress) => void,
+  ): Promise<UnlistenFn> =>
+    listen<ModelDownloadProgress>("model-download-progress", (event) => {
+      callback(event.payload);
+    }),
+
+  /** Emitted at startup when the models directory and database disagree. */
+  onModelFilesReport: (
+    callback: (report: ModelFilesReport) => void,
+  ): Promise<UnlistenFn> =>
+    listen<ModelFilesReport>("model-files-report", (event) => {
+      callback(event.payload);
+    }),
+
+  /** Emitted for each model file that fails verification; offer a re-download. */
+  onModelCorrupt: (
+    callback: (model: CorruptModel) => void,
+  ): Promise<UnlistenFn> =>
+    listen<CorruptModel>("model-corrupt", (event) => {
+      callback(event.payload);
+    }),
+
+  onModelLoading: (
+    callback: (event: ModelEvent) => void,
+  ): Promise<UnlistenFn> =>
+    listen<ModelEvent>("model-loading", (event) => {
+      callback(event.payload);
+    }),
+
+  onModelReady: (
+    callback: (event: ModelEvent) => void,
+  ): Promise<UnlistenFn> =>
+    listen<ModelEvent>("model-ready", (event) => {
+      callback(event.payload);
+    }),
 };
@@ -1,16 +1,50 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
-export type RecordingStateValue = "idle" | "recording" | "processing";
+export type RecordingStateValue =
+  | "idle"
+  | "recording"
+  | "paused"
+  | "processing"
+  | "error"
+  | "cancelled";
 
 export interface RecordingStateUpdate {
   state: RecordingStateValue;
   sessionId?: string;
 }
 
+/** Entry of `Transcription.meta.pausedIntervals`, in ms from the start. */
+export interface PausedInterval {
+  startMs: number;
+  endMs: number;
+}
+
+/** Payload of the `transcription-partial` event. */
+export interface TranscriptionPartial {
+  sessionId: string;
+  /** Committed text; will not change for the rest of the session. */
+  stableText: string;
+  /** Best guess for the utterance still being spoken. */
+  tentativeText: string;
+}
+
+/** Payload of the `transcription-progress` event. */
+export interface TranscriptionProgress {
+  sessionId: string;
+  stage: "decoding" | "transcribing" | "formatting";
+  /** Percentage of the stage done, when known. */
+  percent?: number;
+}
+
 export const recordingApi = {
   signalStart: () => invoke<RecordingStateUpdate>("signal_start"),
 
+  /** Audio chunks sent while paused are discarded. */
+  signalPause: () => invoke<RecordingStateUpdate>("signal_pause"),
+
+  signalResume: () => invoke<RecordingStateUpdate>("signal_resume"),
+
   signalStop: () => invoke<RecordingStateUpdate>("signal_stop"),
 
   getRecordingState: () => invoke<RecordingStateUpdate>("get_recording_state"),
@@ -48,6 +82,20 @@ export const recordingApi = {
       callback(event.payload);
     }),
 
+  onTranscriptionPartial: (
+    callback: (partial: TranscriptionPartial) => void,
+  ): Promise<UnlistenFn> =>
+    listen<TranscriptionPartial>("transcription-partial", (event) => {
+      callback(event.payload);
+    }),
+
+  onTranscriptionProgress: (
+    callback: (progress: TranscriptionProgress) => void,
+  ): Promise<UnlistenFn> =>
+    listen<TranscriptionProgress>("transcription-progress", (event) => {
+      callback(event.payload);
+    }),
+
   onTranscriptionCompleted: (
     callback: (text: string) => void,
   ): Promise<UnlistenFn> =>
@@ -11,6 +11,8 @@ export interface AppSettingsData {
   preferences?: AppPreferences;
   onboarding?: OnboardingSettings;
   telemetry?: TelemetrySettings;
+  textNormalization?: TextNormalizationSettings;
+  inverseTextNormalization?: ItnSettings;
 }
 
 export interface FormatterConfig {
@@ -28,6 +30,12 @@ export interface TranscriptionSettings {
   language?: string;
   autoTranscribe?: boolean;
   preloadWhisperModel?: boolean;
+  /** Maximum characters of recent context used as the whisper prompt; 0 disables. */
+  initialPromptLength?: number;
+  /** Recent transcriptions the whisper prompt draws on. Defaults to 10. */
+  initialPromptHistory?: number;
+  /** Seconds an unused speech model stays loaded; 0 keeps it loaded. Defaults to 300. */
+  modelIdleTimeoutSecs?: number;
 }
 
 export interface RecordingSettings {
@@ -55,6 +63,8 @@ export interface OllamaConfig {
 export interface DictationSettings {
   autoDetectEnabled: boolean;
   selectedLanguage: string;
+  /** Languages auto-detection may choose from; unset or empty allows all. */
+  autoDetectLanguages?: string[];
 }
 
 export interface AppPreferences {
@@ -75,6 +85,38 @@ export interface TelemetrySettings {
   enabled?: boolean;
 }
 
+/** House-style normalization of the final text; unset fields use defaults. */
+export interface TextNormalizationSettings {
+  /** Default: false. */
+  enabled?: boolean;
+  /** Width of letters and digits. Default: "halfwidth". */
+  alphanumericWidth?: "keep" | "halfwidth" | "fullwidth";
+  /** "touten" is 、。 and "comma" is ，．. Default: "keep". */
+  punctuationStyle?: "keep" | "touten" | "comma";
+  /** Convert ｶﾀｶﾅ to カタカナ. Default: true. */
+  fixHalfwidthKatakana?: boolean;
+  /** Spaces between Japanese and Latin text. Default: "keep". */
+  latinSpacing?: "keep" | "insert" | "remove";
+}
+
+/** Spoken Japanese numbers in written form; unset fields use defaults. */
+export interface ItnSettings {
+  /** Default: false. */
+  enabled?: boolean;
+  /** "3,500円" rather than "3500円". Default: true. */
+  digitGrouping?: boolean;
+  /** "1万2000円" rather than "12,000円". Default: true. */
+  keepLargeUnits?: boolean;
+  /** "3,500円" or "¥3,500". Default: "suffix". */
+  currencyStyle?: "suffix" | "symbol";
+  /** "50%", "50％" or "50パーセント". Default: "halfwidth". */
+  percentStyle?: "halfwidth" | "fullwidth" | "katakana";
+  /** "2024年3月5日" or "2024/3/5". Default: "kanji". */
+  dateStyle?: "kanji" | "slash";
+  /** "15時30分" or "15:30". Default: "kanji". */
+  timeStyle?: "kanji" | "colon";
+}
+
 export const settingsApi = {
   getSettings: () => invoke<AppSettingsData>("get_settings"),
 
@@ -115,4 +157,17 @@ export const settingsApi = {
     invoke<void>("set_preferences", { preferences }),
 
   syncAutoLaunch: () => invoke<void>("sync_auto_launch"),
+
+  getTextNormalizationSettings: () =>
+    invoke<TextNormalizationSettings | null>(
+      "get_text_normalization_settings",
+    ),
+
+  setTextNormalizationSettings: (textNormalization: TextNormalizationSettings) =>
+    invoke<void>("set_text_normalization_settings", { textNormalization }),
+
+  getItnSettings: () => invoke<ItnSettings | null>("get_itn_settings"),
+
+  setItnSettings: (itn: ItnSettings) =>
+    invoke<void>("set_itn_settings", { itn }),
 };
@@ -0,0 +1,48 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * literal: plain find and replace.
+ * regex: regular expression; the replacement may use `$1` or `${name}`.
+ * snippet: spoken trigger phrase expanded into the replacement text.
+ */
+export type RuleKind = "literal" | "regex" | "snippet";
+
+export interface TextRule {
+  id: number;
+  kind: RuleKind;
+  pattern: string;
+  replacement: string;
+  position: number;
+  enabled: boolean;
+  createdAt: number;
+  updatedAt: number;
+}
+
+export interface TextRuleInput {
+  kind: RuleKind;
+  pattern: string;
+  replacement: string;
+  enabled?: boolean;
+}
+
+/** Entry of `Transcription.meta.firedRules`. */
+export interface FiredRule {
+  id: number;
+  kind: RuleKind;
+  count: number;
+}
+
+export const textRulesApi = {
+  getTextRules: () => invoke<TextRule[]>("get_text_rules"),
+
+  addTextRule: (input: TextRuleInput) =>
+    invoke<number>("add_text_rule", { input }),
+
+  updateTextRule: (id: number, input: TextRuleInput) =>
+    invoke<void>("update_text_rule", { id, input }),
+
+  reorderTextRules: (ids: number[]) =>
+    invoke<void>("reorder_text_rules", { ids }),
+
+  deleteTextRule: (id: number) => invoke<void>("delete_text_rule", { id }),
+};
@@ -15,6 +15,18 @@ export interface Transcription {
   updatedAt: number;
 }
 
+export interface TranscriptionSegment {
+  id: number;
+  transcriptionId: number;
+  segmentIndex: number;
+  startMs: number;
+  endMs: number;
+  text: string;
+  noSpeechProb?: number;
+  avgLogprob?: number;
+  createdAt: number;
+}
+
 export interface GetTranscriptionsOptions {
   limit?: number;
   offset?: number;
@@ -24,6 +36,7 @@ export interface CreateTranscriptionInput {
   text: string;
   language?: string;
   audioFile?: string;
+  confidence?: number;
   duration?: number;
   speechModel?: string;
   formattingModel?: string;
@@ -40,6 +53,11 @@ export const transcriptionsApi = {
   saveTranscription: (input: CreateTranscriptionInput) =>
     invoke<number>("save_transcription", { input }),
 
+  getTranscriptionSegments: (transcriptionId: number) =>
+    invoke<TranscriptionSegment[]>("get_transcription_segments", {
+      transcriptionId,
+    }),
+
   deleteTranscription: (id: number) =>
     invoke<void>("delete_transcription", { id }),
 
@@ -0,0 +1,32 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export interface VocabularyTerm {
+  id: number;
+  term: string;
+  /** Pronunciation in kana; occurrences are corrected to the term too. */
+  reading?: string;
+  /** Text substituted for the term; the term itself if unset. */
+  replacement?: string;
+  createdAt: number;
+  updatedAt: number;
+}
+
+export interface NewVocabularyTerm {
+  term: string;
+  reading?: string;
+  replacement?: string;
+}
+
+export const vocabularyApi = {
+  getVocabulary: () => invoke<VocabularyTerm[]>("get_vocabulary"),
+
+  addVocabularyTerm: (input: NewVocabularyTerm) =>
+    invoke<number>("add_vocabulary_term", { input }),
+
+  removeVocabularyTerm: (id: number) =>
+    invoke<void>("remove_vocabulary_term", { id }),
+
+  /** Import a CSV/TSV file of `term,reading,replacement` lines. */
+  importVocabulary: (path: string) =>
+    invoke<number>("import_vocabulary", { path }),
+};
@@ -39,26 +39,26 @@ interface AvailableModel {
 
 const BUNDLED_MODELS: AvailableModel[] = [
   {
-    id: "large-v3-turbo",
+    id: "whisper-large-v3-turbo",
     name: "Whisper large-v3-turbo",
     size: "~809 MB",
     description: "高精度・高速。日本語に最適。",
     recommended: true,
   },
   {
-    id: "large-v3",
+    id: "whisper-large-v3",
     name: "Whisper large-v3",
     size: "~1.5 GB",
     description: "最高精度モデル。",
   },
   {
-    id: "medium",
+    id: "whisper-medium",
     name: "Whisper medium",
     size: "~1.4 GB",
     description: "バランスの取れたモデル。",
   },
   {
-    id: "small",
+    id: "whisper-small",
     name: "Whisper small",
     size: "~461 MB",
     description: "軽量・高速。",
@@ -66,7 +66,7 @@ const BUNDLED_MODELS: AvailableModel[] = [
 ];
 
 const ModelStep: React.FC<{ onNext: () => void }> = ({ onNext }) => {
-  const [selected, setSelected] = useState<string>("large-v3-turbo");
+  const [selected, setSelected] = useState<string>("whisper-large-v3-turbo");
   const [downloading, setDownloading] = useState(false);
 
   const handleSelect = async () => {
@@ -110,15 +110,35 @@ const Widget: React.FC = () => {
     }
   }, []);
 
+  const togglePause = useCallback(async () => {
+    const mediaRecorder = mediaRecorderRef.current;
+    try {
+      if (recordingState === "recording") {
+        await recordingApi.signalPause();
+        mediaRecorder?.pause();
+      } else if (recordingState === "paused") {
+        await recordingApi.signalResume();
+        mediaRecorder?.resume();
+      }
+    } catch (err) {
+      console.error("Failed to pause or resume recording:", err);
+    }
+  }, [recordingState]);
+
   const handleClick = useCallback(() => {
-    if (recordingState === "idle") {
+    if (
+      recordingState === "idle" ||
+      recordingState === "error" ||
+      recordingState === "cancelled"
+    ) {
       startRecording();
-    } else if (recordingState === "recording") {
+    } else if (recordingState === "recording" || recordingState === "paused") {
       stopRecording();
     }
   }, [recordingState, startRecording, stopRecording]);
 
   const isActive = recordingState === "recording";
+  const isPaused = recordingState === "paused";
   const isProcessing = recordingState === "processing";
 
   return (
@@ -151,6 +171,17 @@ const Widget: React.FC = () => {
         )}
       </button>
 
+      {(isActive || isPaused) && (
+        <button
+          onClick={togglePause}
+          className="flex items-center justify-center w-8 h-8 rounded-full text-xs"
+          style={{ background: "rgba(0,0,0,0.05)" }}
+          aria-label={isPaused ? "録音を再開" : "録音を一時停止"}
+        >
+          {isPaused ? "▶" : "❚❚"}
+        </button>
+      )}
+
       {lastTranscription && (
         <span
           className="text-sm max-w-xs truncate"